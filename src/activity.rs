@@ -0,0 +1,319 @@
+use crate::{check, Data, Database, Food, Weight, WeightUnit};
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate};
+use ini::{Ini, WriteOption};
+use std::path::Path;
+use std::str::FromStr;
+
+// The rate at which an activity burns energy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rate {
+    // kcal burned per minute, the same for everyone.
+    KcalPerMin(f32),
+    // Metabolic equivalent of task: how many times more energy the activity uses
+    // than sitting still, which burns about 1 kcal per kg of body weight per hour.
+    Met(f32),
+}
+
+impl Default for Rate {
+    fn default() -> Self {
+        Self::KcalPerMin(0.0)
+    }
+}
+
+// Activity is a kind of exercise, such as running, and how much energy it burns.
+// For example:
+// ```
+// name = Running
+// met = 9.8
+// ```
+// Or, with a rate that doesn't depend on weight:
+// ```
+// name = Rowing machine
+// kcal_per_min = 8.5
+// ```
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Activity {
+    pub name: String,
+    pub rate: Rate,
+}
+
+impl Activity {
+    // The kcal burned doing this activity for some minutes.
+    // MET rates need the weight of the person doing it.
+    pub fn kcal(&self, minutes: f32, weight: Option<&Weight>) -> Result<f32> {
+        match self.rate {
+            Rate::KcalPerMin(rate) => Ok(rate * minutes),
+            Rate::Met(met) => {
+                let Some(weight) = weight else {
+                    bail!(
+                        "{} burns energy based on weight, record it with `nosh weigh`",
+                        self.name
+                    );
+                };
+                Ok(met * weight.to(WeightUnit::Kg) * minutes / 60.0)
+            }
+        }
+    }
+}
+
+impl Data for Activity {
+    type Key = str;
+    const DIR: &str = "activity";
+    const GUIDE: &str = "\
+##! An activity has a name, and the energy it burns as either a MET,
+##! which is scaled by your weight, or kcal per minute:
+##!   name = Running
+##!   met = 9.8
+##! or:
+##!   name = Rowing machine
+##!   kcal_per_min = 8.5
+";
+
+    fn path(key: &str) -> std::path::PathBuf {
+        [Self::DIR, key]
+            .iter()
+            .collect::<std::path::PathBuf>()
+            .with_extension("txt")
+    }
+
+    fn load(
+        mut r: impl std::io::BufRead,
+        _load_food: impl FnMut(&str) -> Result<Option<Food>>,
+    ) -> Result<Self> {
+        let ini = Ini::read_from(&mut r)?;
+        log::trace!("Parsing: {ini:?}");
+        let sec = ini.general_section();
+        let get = |k| -> Result<Option<f32>> {
+            sec.get(k)
+                .map(|v| v.parse().with_context(|| format!("Invalid {k} {v:?}")))
+                .transpose()
+        };
+        let rate = match (get("met")?, get("kcal_per_min")?) {
+            (Some(met), None) => Rate::Met(met),
+            (None, Some(rate)) => Rate::KcalPerMin(rate),
+            _ => bail!("Activity must have one of met or kcal_per_min"),
+        };
+        Ok(Self {
+            name: sec.get("name").unwrap_or_default().into(),
+            rate,
+        })
+    }
+
+    fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
+        let mut ini = Ini::new();
+        let mut sec = ini.with_general_section();
+        sec.set("name", &self.name);
+        match self.rate {
+            Rate::Met(met) => sec.set("met", met.to_string()),
+            Rate::KcalPerMin(rate) => sec.set("kcal_per_min", rate.to_string()),
+        };
+        ini.write_to_opt(
+            w,
+            WriteOption {
+                line_separator: ini::LineSeparator::CR,
+                kv_separator: " = ",
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+}
+
+// How much of an activity was done: for a time, or burning a known amount of energy,
+// such as from a fitness tracker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effort {
+    Minutes(f32),
+    Kcal(f32),
+}
+
+// An effort can be parsed from strings like "45min", "1.5 h" or "320kcal".
+impl FromStr for Effort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let idx = s
+            .find(|c: char| c != '.' && !c.is_ascii_digit())
+            .with_context(|| format!("{s:?} needs a unit, such as 45min, 1h or 320kcal"))?;
+        let (size, unit) = s.split_at(idx);
+        let size: f32 = size
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid amount {s:?}"))?;
+        Ok(match unit.trim().to_lowercase().as_str() {
+            "m" | "min" | "mins" | "minute" | "minutes" => Self::Minutes(size),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Self::Minutes(size * 60.0),
+            "kcal" | "cal" | "calories" => Self::Kcal(size),
+            unit => bail!("Unknown unit {unit:?}, expected min, h or kcal"),
+        })
+    }
+}
+
+impl std::fmt::Display for Effort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Minutes(m) => write!(f, "{m} min"),
+            Self::Kcal(k) => write!(f, "{k} kcal"),
+        }
+    }
+}
+
+// An activity done on a day, by its key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExerciseEntry {
+    pub key: String,
+    pub effort: Effort,
+    // The kcal burned, recorded when the exercise is added, so later changes to
+    // the activity or weight don't change past days. None if written by hand.
+    pub kcal: Option<f32>,
+}
+
+impl ExerciseEntry {
+    // Parse an entry from its line, such as "running = 45 min | 412 kcal".
+    pub(crate) fn parse(key: &str, value: &str) -> Result<Self> {
+        let (effort, kcal) = match value.split_once('|') {
+            Some((effort, kcal)) => (effort, Some(kcal)),
+            None => (value, None),
+        };
+        let kcal = match kcal.map(str::parse::<Effort>).transpose()? {
+            None => None,
+            Some(Effort::Kcal(kcal)) => Some(kcal),
+            Some(Effort::Minutes(_)) => bail!("Expected kcal after |, such as 412 kcal"),
+        };
+        Ok(Self {
+            key: key.into(),
+            effort: effort.parse()?,
+            kcal,
+        })
+    }
+
+    // The value of the entry's line. Kcal is only written for efforts given in time.
+    fn value(&self) -> String {
+        match (self.effort, self.kcal) {
+            (Effort::Minutes(_), Some(kcal)) => format!("{} | {kcal:.0} kcal", self.effort),
+            _ => self.effort.to_string(),
+        }
+    }
+}
+
+// The key of exercise that is only recorded as kcal, with no activity.
+pub const EXERCISE_KEY: &str = "exercise";
+
+// Exercise is a record of activities done during a day.
+// It is a list of "activity = effort" lines, for example:
+// ```
+// running = 45 min | 412 kcal
+// exercise = 320 kcal
+// ```
+// An effort given in time is followed by the kcal it burned, worked out when it was added.
+// Without that, the activity must be in the database, to know its rate.
+// One given in kcal doesn't, so any key may be used, such as "exercise".
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Exercise(pub Vec<ExerciseEntry>);
+
+impl Data for Exercise {
+    type Key = NaiveDate;
+    const DIR: &str = "exercise";
+    const GUIDE: &str = "\
+##! Each line is an activity and how long it was done for, or the kcal it burned.
+##! Times are followed by the kcal burned, worked out from the activity when added:
+##!   running = 45 min | 412 kcal
+##!   exercise = 320 kcal
+";
+
+    fn path(key: &NaiveDate) -> std::path::PathBuf {
+        format!(
+            "exercise/{:04}/{:02}/{:02}.txt",
+            key.year(),
+            key.month(),
+            key.day()
+        )
+        .into()
+    }
+
+    fn load(
+        mut r: impl std::io::BufRead,
+        _load_food: impl FnMut(&str) -> Result<Option<Food>>,
+    ) -> Result<Self> {
+        let ini = Ini::read_from(&mut r)?;
+        log::trace!("Parsing: {ini:?}");
+        let mut res = vec![];
+        for (k, v) in ini.general_section() {
+            res.push(ExerciseEntry::parse(k, v).with_context(|| format!("Activity {k}"))?);
+        }
+        Ok(Self(res))
+    }
+
+    fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
+        let mut ini = Ini::new();
+        let mut sec = ini.with_general_section();
+        for entry in &self.0 {
+            sec.add(&entry.key, entry.value());
+        }
+        ini.write_to_opt(
+            w,
+            WriteOption {
+                line_separator: ini::LineSeparator::CR,
+                kv_separator: " = ",
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    fn check(data: &Database, path: &Path, text: &str) -> Vec<check::Problem> {
+        check::exercise(data, path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_effort() {
+        let parse = |s: &str| s.parse::<Effort>().unwrap();
+        assert_eq!(parse("45min"), Effort::Minutes(45.0));
+        assert_eq!(parse("1.5 h"), Effort::Minutes(90.0));
+        assert_eq!(parse("320kcal"), Effort::Kcal(320.0));
+        assert!("45".parse::<Effort>().is_err());
+        assert!("45 laps".parse::<Effort>().is_err());
+    }
+
+    #[test]
+    fn test_activity_kcal() {
+        let load = |s: &str| Activity::load(s.as_bytes(), |_| Ok(None)).unwrap();
+        let run = load("name = Running\nmet = 10\n");
+        let weight = Weight {
+            value: 80.0,
+            unit: WeightUnit::Kg,
+        };
+        assert_eq!(run.kcal(30.0, Some(&weight)).unwrap(), 400.0);
+        assert!(run.kcal(30.0, None).is_err());
+
+        let row = load("name = Rowing\nkcal_per_min = 8.5\n");
+        assert_eq!(row.kcal(20.0, None).unwrap(), 170.0);
+
+        assert!(Activity::load("name = Nothing\n".as_bytes(), |_| Ok(None)).is_err());
+    }
+
+    #[test]
+    fn test_exercise_round_trip() {
+        let text = "running = 45 min | 412 kcal\nrowing = 20 min\nexercise = 320 kcal\n";
+        let exercise = Exercise::load(text.as_bytes(), |_| Ok(None)).unwrap();
+        assert_eq!(
+            exercise.0.iter().map(|e| e.kcal).collect::<Vec<_>>(),
+            vec![Some(412.0), None, None]
+        );
+        let mut buf = vec![];
+        exercise.save(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), text);
+
+        assert!(Exercise::load("running = 45 min | 1h\n".as_bytes(), |_| Ok(None)).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{Data, Food};
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate};
+use ini::{Ini, WriteOption};
+use std::str::FromStr;
+
+// A unit of body weight.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WeightUnit {
+    #[default]
+    Kg,
+    Lb,
+}
+
+const LB_PER_KG: f32 = 2.204_622_6;
+
+impl std::fmt::Display for WeightUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeightUnit::Kg => write!(f, "kg"),
+            WeightUnit::Lb => write!(f, "lb"),
+        }
+    }
+}
+
+impl FromStr for WeightUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "kg" | "kgs" | "kilogram" | "kilograms" => Ok(Self::Kg),
+            "lb" | "lbs" | "pound" | "pounds" => Ok(Self::Lb),
+            _ => bail!("Unknown weight unit {s:?}, expected kg or lb"),
+        }
+    }
+}
+
+// A body weight, in the unit it was recorded in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weight {
+    pub value: f32,
+    pub unit: WeightUnit,
+}
+
+impl Weight {
+    // Parse a weight such as "81.3kg" or "179 lb".
+    // If there is no unit, the default is used.
+    pub fn parse(s: &str, default: WeightUnit) -> Result<Self> {
+        let (value, unit) = split_weight(s)?;
+        Ok(Self {
+            value,
+            unit: unit.unwrap_or(default),
+        })
+    }
+
+    // The weight in the given unit.
+    pub fn to(&self, unit: WeightUnit) -> f32 {
+        match (self.unit, unit) {
+            (WeightUnit::Kg, WeightUnit::Lb) => self.value * LB_PER_KG,
+            (WeightUnit::Lb, WeightUnit::Kg) => self.value / LB_PER_KG,
+            _ => self.value,
+        }
+    }
+}
+
+// A weight in a file must have a unit, as the default may change.
+impl FromStr for Weight {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match split_weight(s)? {
+            (value, Some(unit)) => Ok(Self { value, unit }),
+            (_, None) => bail!("Weight {s:?} has no unit, expected kg or lb"),
+        }
+    }
+}
+
+// Split a weight into its value and unit, if any.
+fn split_weight(s: &str) -> Result<(f32, Option<WeightUnit>)> {
+    let s = s.trim();
+    let (value, unit) = match s.find(|c: char| c != '.' && !c.is_ascii_digit()) {
+        Some(idx) => {
+            let (value, unit) = s.split_at(idx);
+            (value.trim(), Some(unit.parse()?))
+        }
+        None => (s, None),
+    };
+    let value = value
+        .parse()
+        .with_context(|| format!("Invalid weight {s:?}"))?;
+    Ok((value, unit))
+}
+
+impl std::fmt::Display for Weight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+// Body is a record of body measurements taken on a day, such as weight.
+// It is a list of "measurement = value" lines, for example:
+// ```
+// weight = 81.3 kg
+// waist = 86 cm
+// ```
+// Weight is parsed, so it can be shown in reports. Other measurements are kept as written.
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Body {
+    pub weight: Option<Weight>,
+    pub measurements: Vec<(String, String)>,
+}
+
+impl Data for Body {
+    type Key = NaiveDate;
+    const DIR: &str = "body";
+    const GUIDE: &str = "\
+##! Each line is a measurement taken on this day, and its value.
+##! Weight is in kg or lb, and other measurements may be anything:
+##!   weight = 81.3 kg
+##!   waist = 86 cm
+";
+
+    fn path(key: &NaiveDate) -> std::path::PathBuf {
+        format!(
+            "body/{:04}/{:02}/{:02}.txt",
+            key.year(),
+            key.month(),
+            key.day()
+        )
+        .into()
+    }
+
+    fn load(
+        mut r: impl std::io::BufRead,
+        _load_food: impl FnMut(&str) -> Result<Option<Food>>,
+    ) -> Result<Self> {
+        let ini = Ini::read_from(&mut r)?;
+        log::trace!("Parsing: {ini:?}");
+        let mut res = Self::default();
+        for (k, v) in ini.general_section() {
+            match k {
+                "weight" => res.weight = Some(v.parse()?),
+                _ => res.measurements.push((k.into(), v.into())),
+            }
+        }
+        Ok(res)
+    }
+
+    fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
+        let mut ini = Ini::new();
+        let mut sec = ini.with_general_section();
+        if let Some(weight) = &self.weight {
+            sec.add("weight", weight.to_string());
+        }
+        for (k, v) in &self.measurements {
+            sec.add(k, v);
+        }
+        ini.write_to_opt(
+            w,
+            WriteOption {
+                line_separator: ini::LineSeparator::CR,
+                kv_separator: " = ",
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+}
+
+impl Body {
+    // Set a measurement, replacing any earlier value.
+    pub fn measure(&mut self, name: &str, value: &str) {
+        match self.measurements.iter_mut().find(|(k, _)| k == name) {
+            Some((_, v)) => *v = value.into(),
+            None => self.measurements.push((name.into(), value.into())),
+        }
+    }
+}
+
+// The number of days averaged for a weight trend.
+pub const TREND_DAYS: i64 = 7;
+
+// The trend of a weight on a date: the average of the weights recorded in the
+// TREND_DAYS days up to and including it. Daily weights vary with water and food,
+// so the trend shows the direction of change better.
+pub fn trend(weights: &[(NaiveDate, f32)], date: NaiveDate) -> Option<f32> {
+    let start = date - chrono::Duration::days(TREND_DAYS - 1);
+    let window: Vec<_> = weights
+        .iter()
+        .filter(|(d, _)| (start..=date).contains(d))
+        .map(|(_, w)| w)
+        .collect();
+    if window.is_empty() {
+        return None;
+    }
+    Some(window.iter().copied().sum::<f32>() / window.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_weight() {
+        let weight = |value, unit| Weight { value, unit };
+        let parse = |s| Weight::parse(s, WeightUnit::Lb).unwrap();
+        assert_eq!(parse("81.3kg"), weight(81.3, WeightUnit::Kg));
+        assert_eq!(parse("179 lbs"), weight(179.0, WeightUnit::Lb));
+        assert_eq!(parse("179"), weight(179.0, WeightUnit::Lb));
+        assert!(Weight::parse("81.3 stone", WeightUnit::Kg).is_err());
+        assert!((weight(100.0, WeightUnit::Kg).to(WeightUnit::Lb) - 220.46).abs() < 0.01);
+        assert!((weight(220.46, WeightUnit::Lb).to(WeightUnit::Kg) - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_body_round_trip() {
+        let text = "weight = 81.3 kg\nwaist = 86 cm\n";
+        let body = Body::load(text.as_bytes(), |_| Ok(None)).unwrap();
+        assert_eq!(body.weight, Some("81.3kg".parse().unwrap()));
+        assert_eq!(body.measurements, vec![("waist".into(), "86 cm".into())]);
+        let mut buf = vec![];
+        body.save(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), text);
+
+        assert!(Body::load("weight = 81.3\n".as_bytes(), |_| Ok(None)).is_err());
+    }
+
+    #[test]
+    fn test_trend() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 7, d).unwrap();
+        let weights = [(day(1), 80.0), (day(2), 82.0), (day(8), 84.0)];
+        assert_eq!(trend(&weights, day(1)), Some(80.0));
+        assert_eq!(trend(&weights, day(5)), Some(81.0));
+        // The window is 7 days, so the 1st is left out on the 8th.
+        assert_eq!(trend(&weights, day(8)), Some(83.0));
+        assert_eq!(trend(&weights, day(20)), None);
+    }
+}
@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    journal, refs, Data, Database, Effort, Exercise, ExerciseEntry, Food, Journal, Serving,
+};
+
+// A problem found in a data file.
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Problem {
+    pub path: PathBuf,       // relative to the database root
+    pub line: Option<usize>, // starts at 1, if the problem is on a particular line
+    pub message: String,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.path.display(), self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+// Check the text of a food file, including that its aliases don't belong to other foods.
+pub fn food(data: &Database, path: &Path, text: &str) -> Vec<Problem> {
+    let res = check_refs(data, path, refs::recipe_keys(text));
+    if !res.is_empty() {
+        return res;
+    }
+    // Ingredients were checked above, so don't load them again.
+    let food = match Food::load(text.as_bytes(), |_| Ok(Some(Food::default()))) {
+        Ok(food) => food,
+        Err(err) => return vec![load_problem(path, err)],
+    };
+    let key = path
+        .file_stem()
+        .and_then(|k| k.to_str())
+        .unwrap_or_default();
+    let messages = match data.alias_clashes(key, &food) {
+        Ok(clashes) => clashes
+            .into_iter()
+            .map(|(alias, other)| format!("Alias {alias:?} is already used by food {other:?}"))
+            .collect(),
+        Err(err) => vec![format!("Failed to check aliases: {err:#}")],
+    };
+    let line = refs::keys(text, None)
+        .find(|r| r.key == "aliases")
+        .map(|r| r.line);
+    messages
+        .into_iter()
+        .map(|message| Problem {
+            path: path.into(),
+            line,
+            message,
+        })
+        .collect()
+}
+
+// Check the text of a journal file.
+pub fn journal(data: &Database, path: &Path, text: &str) -> Vec<Problem> {
+    // Only the serving refers to the food, the time and note are checked below.
+    let refs = refs::journal_keys(text).map(|r| refs::Ref {
+        value: journal::split_value(r.value).0,
+        ..r
+    });
+    let mut res = check_refs(data, path, refs);
+    for r in refs::keys(text, None) {
+        if let Some(Err(err)) = journal::split_value(r.value).1.map(journal::parse_time) {
+            res.push(Problem {
+                path: path.into(),
+                line: Some(r.line),
+                message: err.to_string(),
+            });
+        }
+    }
+    res.sort_by_key(|p| p.line);
+    if res.is_empty() {
+        res = load::<Journal>(path, text);
+    }
+    res
+}
+
+// Check that the text of a file loads, such as body measurements or goals.
+// Referenced foods aren't loaded, so they should be checked separately.
+pub fn load<T: Data>(path: &Path, text: &str) -> Vec<Problem> {
+    match T::load(text.as_bytes(), |_| Ok(Some(Food::default()))) {
+        Ok(_) => vec![],
+        Err(err) => vec![load_problem(path, err)],
+    }
+}
+
+// Check the text of an exercise file, including that activities done for a time exist.
+pub fn exercise(data: &Database, path: &Path, text: &str) -> Vec<Problem> {
+    let mut res = vec![];
+    for r in refs::keys(text, None) {
+        let mut problem = |message| {
+            res.push(Problem {
+                path: path.into(),
+                line: Some(r.line),
+                message,
+            })
+        };
+        // Only a time without the kcal it burned needs the activity.
+        match ExerciseEntry::parse(r.key, r.value) {
+            Ok(ExerciseEntry {
+                effort: Effort::Minutes(_),
+                kcal: None,
+                ..
+            }) => match data.load_activity(r.key) {
+                Ok(Some(_)) => {}
+                Ok(None) => problem(format!("No activity with key {:?}", r.key)),
+                Err(err) => problem(format!("Failed to load activity {:?}: {err:#}", r.key)),
+            },
+            Ok(_) => {}
+            Err(err) => problem(format!("{err:#}")),
+        }
+    }
+    if res.is_empty() {
+        res = load::<Exercise>(path, text);
+    }
+    res
+}
+
+// Check that each referenced food exists, and that its serving is valid for that food.
+fn check_refs<'a>(
+    data: &Database,
+    path: &Path,
+    refs: impl Iterator<Item = refs::Ref<'a>>,
+) -> Vec<Problem> {
+    let mut res = vec![];
+    for r in refs {
+        let mut problem = |message| {
+            res.push(Problem {
+                path: path.into(),
+                line: Some(r.line),
+                message,
+            })
+        };
+        let food = match data.load_food(r.key) {
+            Ok(Some(food)) => food,
+            Ok(None) => {
+                problem(data.food_not_found(r.key).to_string());
+                continue;
+            }
+            Err(err) => {
+                problem(format!("Failed to load food {:?}: {err:#}", r.key));
+                continue;
+            }
+        };
+        match r.value.parse::<Serving>() {
+            Ok(serving) => {
+                if let Err(err) = food.portion(&serving) {
+                    problem(format!("{err:#}"));
+                }
+            }
+            Err(err) => problem(format!("Invalid serving {:?}: {err:#}", r.value)),
+        }
+    }
+    res
+}
+
+pub(crate) fn load_problem(path: &Path, err: anyhow::Error) -> Problem {
+    match err.downcast_ref::<ini::Error>() {
+        Some(ini::Error::Parse(err)) => Problem {
+            path: path.into(),
+            line: Some(err.line),
+            message: err.msg.to_string(),
+        },
+        _ => Problem {
+            path: path.into(),
+            line: None,
+            message: format!("{err:#}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_check_food_aliases() {
+        let (data, _tmp) = crate::tests::setup();
+        let path = Path::new("food/banana.txt");
+        let text = "name = Banana\naliases = plantain, porridge\n[nutrients]\ncarb = 23\n";
+        assert_eq!(
+            food(&data, path, text)
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>(),
+            vec!["food/banana.txt:2: Alias \"porridge\" is already used by food \"oats\""]
+        );
+    }
+
+    #[test]
+    fn test_check_journal() {
+        let (data, _tmp) = crate::tests::setup();
+        let path = Path::new("journal/2024/07/02.txt");
+        let text = "\
+banana = 1 | 8am
+granola = 2 cups | 12:00 | with milk
+oats = 3 spoons | 18:30
+
+[granola]
+name = Granola
+carb = 60
+servings = 0.5 cups
+";
+        let problems = journal(&data, path, text);
+        assert_eq!(
+            problems.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            vec![
+                "journal/2024/07/02.txt:1: Invalid time \"8am\", expected HH:MM",
+                "journal/2024/07/02.txt:3: \
+                 Unknown serving unit spoons, expected one of: cups, g"
+            ]
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-use crate::Food;
+use crate::{check, Database, Food};
 
 // A type that can be stored in the Database.
 pub trait Data: Sized {
@@ -11,8 +11,12 @@ pub trait Data: Sized {
     // The name of the root directory where data of this type is stored;
     const DIR: &str;
 
+    // Help shown below an item when it is edited, as lines starting with "##!".
+    const GUIDE: &str = "";
+
     // Return a relative path to the file where the given key should be stored.
     // This should include DIR as the first component, and should include an extension.
+    // Data kept in a single file, such as Goals, may use DIR as the file name instead.
     fn path(key: &Self::Key) -> std::path::PathBuf;
 
     // Load an item from a reader.
@@ -23,4 +27,11 @@ pub trait Data: Sized {
 
     // Save an item to a reader.
     fn save(&self, w: &mut impl std::io::Write) -> Result<()>;
+
+    // Look for problems in the text of a file that will be saved at path.
+    // By default this only checks that the text loads, and types that refer
+    // to other data, such as the foods in a journal, should check them too.
+    fn check(_data: &Database, path: &std::path::Path, text: &str) -> Vec<check::Problem> {
+        check::load::<Self>(path, text)
+    }
 }
@@ -0,0 +1,234 @@
+use std::io::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::{check, merge, Data, Database};
+
+// Help shown before the guide of every type.
+const PREAMBLE: &str = "\
+##! Lines starting with ##! are help, and are removed when saved.
+##! Comments start with ; or #.
+";
+
+// Edit an item as text, such as in the user's editor, until it loads without problems.
+// The item is written to a temporary file, followed by help on the file format
+// and the guide for its type, then `open` is called to edit it.
+// If the result has problems, they are passed to `retry`, as lines of "line N: problem",
+// which returns true to edit the file again. Otherwise, or if `open` fails, the temporary
+// file is kept, so the changes aren't lost, and an error is returned.
+// The edited text is returned along with the item, so it can be saved with any comments.
+pub fn edit<T: Data + std::fmt::Debug>(
+    data: &Database,
+    key: &T::Key,
+    orig: &T,
+    mut open: impl FnMut(&Path) -> Result<()>,
+    mut retry: impl FnMut(&str) -> Result<bool>,
+) -> Result<(T, String)> {
+    let mut buf = vec![];
+    orig.save(&mut buf)?;
+    let mut text = String::from_utf8(buf)?;
+    if let Some(old) = data.load_text::<T>(key)? {
+        // Start from the saved file, so its comments can be edited too.
+        text = merge::merge(&old, &text);
+    }
+    let mut tmp = tempfile::Builder::new().suffix(".txt").tempfile()?;
+    tmp.write_all(text.as_bytes())?;
+    write!(tmp, "\n{PREAMBLE}")?;
+    if !T::GUIDE.is_empty() {
+        write!(tmp, "##!\n{}", T::GUIDE)?;
+    }
+    tmp.flush()?;
+    log::debug!("Wrote {orig:?} to {tmp:?}");
+
+    loop {
+        if let Err(err) = open(tmp.path()) {
+            // The file may have been edited before the editor failed.
+            let (_, path) = tmp.keep()?;
+            return Err(err.context(format!("Your changes were saved to {}", path.display())));
+        }
+        let text = std::fs::read_to_string(tmp.path())
+            .with_context(|| format!("Read {:?}", tmp.path()))?;
+        let text = strip_guide(&text);
+        let problems = match problems::<T>(data, key, &text) {
+            Ok(new) => {
+                log::debug!("Parsed: {new:?}");
+                return Ok((new, text));
+            }
+            Err(problems) => problems,
+        };
+        if !retry(&problems)? {
+            let (_, path) = tmp.keep()?;
+            anyhow::bail!("{problems}\nYour changes were saved to {}", path.display());
+        }
+    }
+}
+
+// Remove lines of help, starting with "##!", which users are unlikely to start
+// a comment with, unlike "##".
+// They are replaced by blank lines, so problems are reported on the lines the user saw.
+fn strip_guide(text: &str) -> String {
+    let lines: Vec<_> = text
+        .lines()
+        .map(|l| if l.starts_with("##!") { "" } else { l })
+        .collect();
+    let mut res = lines.join("\n").trim_end().to_string();
+    res.push('\n');
+    res
+}
+
+// Load an item from text, or describe every problem with it.
+fn problems<T: Data>(data: &Database, key: &T::Key, text: &str) -> Result<T, String> {
+    let path = T::path(key);
+    let problems = T::check(data, &path, text);
+    let err = if problems.is_empty() {
+        match T::load(text.as_bytes(), |key| data.load_ingredient(key)) {
+            Ok(new) => return Ok(new),
+            Err(err) => vec![check::load_problem(&path, err)],
+        }
+    } else {
+        problems
+    };
+    Err(err
+        .iter()
+        .map(|p| match p.line {
+            Some(line) => format!("line {line}: {}", p.message),
+            None => p.message.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+// Open a file in the editor given by $EDITOR, waiting for it to exit.
+pub fn open_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("EDITOR").context("EDITOR not set")?;
+    let editor = which::which(editor)?;
+    let mut cmd = std::process::Command::new(editor);
+    cmd.arg(path)
+        .stdin(std::process::Stdio::inherit())
+        .stdout(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::inherit());
+    log::debug!("Running {cmd:?}");
+
+    let status = cmd.spawn()?.wait()?;
+    anyhow::ensure!(status.success(), "Editor exited with code: {status:?}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Food;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_edit_retry() {
+        let (data, _tmp) = crate::tests::setup();
+        let mut edits = vec![
+            "name = Granola\n[nutrients]\ncarb = 60\n",
+            "name = Granola\n[ingredients]\noast = 1\nbanana = 1 spoon\n",
+        ];
+        let mut errors = vec![];
+        let (food, _) = edit::<Food>(
+            &data,
+            "granola",
+            &Food::default(),
+            |path| Ok(std::fs::write(path, edits.pop().unwrap())?),
+            |err| {
+                errors.push(err.to_string());
+                Ok(true)
+            },
+        )
+        .unwrap();
+        assert_eq!(food.name, "Granola");
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with("line 3: No food with key \"oast\""),
+            "{errors:?}"
+        );
+        assert!(
+            errors[0].contains("\nline 4: Unknown serving unit spoon"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_edit_guide() {
+        let (data, _tmp) = crate::tests::setup();
+        let oats = data.load_food("oats").unwrap().unwrap();
+        let (food, text) = edit::<Food>(
+            &data,
+            "oats",
+            &oats,
+            |path| {
+                let text = std::fs::read_to_string(path)?;
+                assert!(text.ends_with(Food::GUIDE), "{text}");
+                assert_eq!(text.matches(PREAMBLE).count(), 1, "{text}");
+                // Break a line of the guide, which shouldn't matter.
+                let text = text.replacen("name = Oats", "## Steel cut\nname = Rolled oats", 1);
+                Ok(std::fs::write(
+                    path,
+                    text.replace("##!   [nutrients]", "##! [nutri"),
+                )?)
+            },
+            |err| panic!("{err}"),
+        )
+        .unwrap();
+        assert_eq!(food.name, "Rolled oats");
+        assert_eq!(food.servings, oats.servings);
+        assert!(
+            text.starts_with("## Steel cut\nname = Rolled oats\n"),
+            "{text}"
+        );
+        assert!(!text.contains("##!"), "{text}");
+    }
+
+    #[test]
+    fn test_edit_open_fails() {
+        let (data, _tmp) = crate::tests::setup();
+        let err = edit::<Food>(
+            &data,
+            "granola",
+            &Food::default(),
+            |path| {
+                std::fs::write(path, "name = Granola\n")?;
+                anyhow::bail!("Editor crashed")
+            },
+            |_| Ok(false),
+        )
+        .unwrap_err();
+        assert_eq!(err.root_cause().to_string(), "Editor crashed");
+        let path = err
+            .to_string()
+            .strip_prefix("Your changes were saved to ")
+            .unwrap()
+            .to_string();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "name = Granola\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_edit_abort() {
+        let (data, _tmp) = crate::tests::setup();
+        let err = edit::<Food>(
+            &data,
+            "granola",
+            &Food::default(),
+            |path| Ok(std::fs::write(path, "name = Granola\n[nutrients\n")?),
+            |_| Ok(false),
+        )
+        .unwrap_err()
+        .to_string();
+        let path = err
+            .lines()
+            .last()
+            .unwrap()
+            .strip_prefix("Your changes were saved to ")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "name = Granola\n[nutrients\n"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::search::{page_size, Page, Provider, Results, SearchFood, SearchNutrient};
+
+// An offline index of foods imported from a Food Data Central bulk download.
+// https://fdc.nal.usda.gov/download-datasets.html
+// Foods are stored in the same form returned by the search API,
+// so they are converted to a Food in the same way as online search results.
+// Every nutrient is kept, so they can all be shown in the details of a result.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Index {
+    foods: Vec<SearchFood>,
+}
+
+// A food as it appears in the JSON downloads.
+// Unlike the search API, nutrients are nested.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadFood {
+    fdc_id: Option<u32>,
+    description: Option<String>,
+    serving_size: Option<f32>,
+    serving_size_unit: Option<String>,
+    household_serving_full_text: Option<String>,
+    gtin_upc: Option<String>,
+    brand_owner: Option<String>,
+    ingredients: Option<String>,
+    #[serde(default)]
+    food_nutrients: Vec<DownloadNutrient>,
+}
+
+#[derive(Deserialize)]
+struct DownloadNutrient {
+    nutrient: DownloadNutrientInfo,
+    amount: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadNutrientInfo {
+    id: u32,
+    name: Option<String>,
+    unit_name: Option<String>,
+}
+
+impl From<DownloadFood> for SearchFood {
+    fn from(value: DownloadFood) -> Self {
+        SearchFood {
+            fdc_id: value.fdc_id,
+            description: value.description,
+            serving_size: value.serving_size,
+            serving_size_unit: value.serving_size_unit,
+            household_serving_full_text: value.household_serving_full_text,
+            gtin_upc: value.gtin_upc,
+            brand_owner: value.brand_owner,
+            ingredients: value.ingredients,
+            food_nutrients: Some(
+                value
+                    .food_nutrients
+                    .into_iter()
+                    .filter_map(|n| {
+                        n.amount.map(|value| SearchNutrient {
+                            nutrient_id: n.nutrient.id,
+                            nutrient_name: n.nutrient.name,
+                            unit_name: n.nutrient.unit_name,
+                            value,
+                        })
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// Rows of the CSV downloads. Only the columns nosh uses are listed.
+#[derive(Deserialize)]
+struct CsvFood {
+    fdc_id: u32,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct CsvNutrient {
+    fdc_id: u32,
+    nutrient_id: u32,
+    amount: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct CsvNutrientInfo {
+    id: u32,
+    name: String,
+    unit_name: String,
+}
+
+#[derive(Deserialize)]
+struct CsvBrandedFood {
+    fdc_id: u32,
+    brand_owner: Option<String>,
+    ingredients: Option<String>,
+    gtin_upc: Option<String>,
+    serving_size: Option<f32>,
+    serving_size_unit: Option<String>,
+    household_serving_fulltext: Option<String>,
+}
+
+impl Index {
+    // Read an FDC download.
+    // The path may be either an extracted CSV download (a directory containing food.csv),
+    // or a JSON download (a single .json file).
+    pub fn import(path: &Path) -> Result<Index> {
+        if path.is_dir() {
+            Self::import_csv(path)
+        } else {
+            Self::import_json(path)
+        }
+    }
+
+    fn import_json(path: &Path) -> Result<Index> {
+        log::debug!("Importing JSON from {path:?}");
+        let file = std::fs::File::open(path).with_context(|| format!("Open {path:?}"))?;
+        // Each download has a single key naming the dataset, e.g. "FoundationFoods".
+        let datasets: HashMap<String, Vec<DownloadFood>> =
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .with_context(|| format!("Parse {path:?}"))?;
+        let foods = datasets
+            .into_iter()
+            .flat_map(|(name, foods)| {
+                log::debug!("Importing {} foods from {name}", foods.len());
+                foods
+            })
+            .map(SearchFood::from)
+            .collect();
+        Ok(Index { foods })
+    }
+
+    fn import_csv(dir: &Path) -> Result<Index> {
+        log::debug!("Importing CSV from {dir:?}");
+        let path = dir.join("food.csv");
+        if !path.exists() {
+            bail!("No food.csv in {dir:?}");
+        }
+
+        // Names of each nutrient, keyed by ID.
+        let mut info = HashMap::<u32, CsvNutrientInfo>::new();
+        let path = dir.join("nutrient.csv");
+        if path.exists() {
+            for row in csv::Reader::from_path(path)?.into_deserialize() {
+                let row: CsvNutrientInfo = row?;
+                info.insert(row.id, row);
+            }
+        }
+
+        let mut nutrients = HashMap::<u32, Vec<SearchNutrient>>::new();
+        for row in csv::Reader::from_path(dir.join("food_nutrient.csv"))?.into_deserialize() {
+            let row: CsvNutrient = row?;
+            if let Some(value) = row.amount {
+                let info = info.get(&row.nutrient_id);
+                nutrients
+                    .entry(row.fdc_id)
+                    .or_default()
+                    .push(SearchNutrient {
+                        nutrient_id: row.nutrient_id,
+                        nutrient_name: info.map(|i| i.name.clone()),
+                        unit_name: info.map(|i| i.unit_name.clone()),
+                        value,
+                    });
+            }
+        }
+
+        // Only the branded dataset has serving information.
+        let mut branded = HashMap::<u32, CsvBrandedFood>::new();
+        let path = dir.join("branded_food.csv");
+        if path.exists() {
+            for row in csv::Reader::from_path(path)?.into_deserialize() {
+                let row: CsvBrandedFood = row?;
+                branded.insert(row.fdc_id, row);
+            }
+        }
+
+        let mut foods = vec![];
+        for row in csv::Reader::from_path(dir.join("food.csv"))?.into_deserialize() {
+            let row: CsvFood = row?;
+            let branded = branded.remove(&row.fdc_id);
+            let branded = branded.as_ref();
+            foods.push(SearchFood {
+                fdc_id: Some(row.fdc_id),
+                description: Some(row.description),
+                serving_size: branded.and_then(|b| b.serving_size),
+                serving_size_unit: branded.and_then(|b| b.serving_size_unit.clone()),
+                household_serving_full_text: branded
+                    .and_then(|b| b.household_serving_fulltext.clone()),
+                gtin_upc: branded.and_then(|b| b.gtin_upc.clone()),
+                brand_owner: branded.and_then(|b| b.brand_owner.clone()),
+                ingredients: branded.and_then(|b| b.ingredients.clone()),
+                food_nutrients: Some(nutrients.remove(&row.fdc_id).unwrap_or_default()),
+            });
+        }
+        Ok(Index { foods })
+    }
+
+    // Add all foods from another index, replacing any with the same FDC ID.
+    pub fn merge(&mut self, other: Index) {
+        let ids: std::collections::HashSet<_> =
+            other.foods.iter().filter_map(|f| f.fdc_id).collect();
+        self.foods
+            .retain(|f| f.fdc_id.is_none_or(|id| !ids.contains(&id)));
+        self.foods.extend(other.foods);
+    }
+
+    pub fn len(&self) -> usize {
+        self.foods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.foods.is_empty()
+    }
+
+    pub fn load(r: impl std::io::Read) -> Result<Index> {
+        Ok(serde_json::from_reader(r)?)
+    }
+
+    pub fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
+        Ok(serde_json::to_writer(w, self)?)
+    }
+}
+
+// Search for a food in an offline Index.
+// A food matches if its description contains every word of the term, ignoring case.
+pub struct OfflineSearch<'a> {
+    pub index: &'a Index,
+    pub page_size: usize,
+}
+
+impl<'a> Provider for OfflineSearch<'a> {
+    fn search(&self, term: &str, page: usize) -> Result<Results> {
+        let page_size = page_size(self.page_size);
+        let words: Vec<_> = term.split_whitespace().map(str::to_lowercase).collect();
+        let matches: Vec<_> = self
+            .index
+            .foods
+            .iter()
+            .filter(|f| {
+                let desc = f.description.as_deref().unwrap_or_default().to_lowercase();
+                words.iter().all(|w| desc.contains(w))
+            })
+            .collect();
+        let foods: Vec<_> = matches
+            .iter()
+            .skip(page.saturating_sub(1) * page_size)
+            .take(page_size)
+            .map(|&f| f.clone())
+            .collect();
+        Ok(Results {
+            hits: Page::from(foods).hits().collect(),
+            page,
+            total_pages: matches.len().div_ceil(page_size),
+            total_hits: matches.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Food, FoodSpec, Nutrients};
+    use pretty_assertions::assert_eq;
+
+    fn search(index: &Index, term: &str, page_size: usize) -> Vec<Vec<Food>> {
+        let search = OfflineSearch { index, page_size };
+        let mut pages = vec![];
+        for page in 1.. {
+            let res = search.search(term, page).unwrap();
+            if res.hits.is_empty() {
+                assert_eq!(res.total_pages, pages.len());
+                return pages;
+            }
+            pages.push(res.hits.into_iter().map(|h| h.food).collect());
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_import_json() {
+        let _ = env_logger::try_init();
+        let index = Index::import(Path::new("tests/testdata/fdc/foundation.json")).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            search(&index, "POTATO raw", 1),
+            vec![
+                vec![Food {
+                    name: "Potatoes, gold, without skin, raw".into(),
+                    spec: FoodSpec::Nutrients(Nutrients {
+                        carb: 16.0,
+                        fat: 0.264,
+                        protein: 1.81,
+                        kcal: 71.6,
+                    }),
+                    servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
+                }],
+                vec![Food {
+                    name: "Potatoes, red, without skin, raw".into(),
+                    spec: FoodSpec::Nutrients(Nutrients {
+                        carb: 16.3,
+                        fat: 0.248,
+                        protein: 2.06,
+                        kcal: 73.4,
+                    }),
+                    servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
+                }],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_csv() {
+        let _ = env_logger::try_init();
+        let index = Index::import(Path::new("tests/testdata/fdc/csv")).unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            search(&index, "pancake", 10),
+            vec![vec![Food {
+                name: "KASIA'S, POTATO PANCAKES, POTATO, POTATO".into(),
+                spec: FoodSpec::Nutrients(Nutrients {
+                    carb: 26.3,
+                    fat: 7.02,
+                    protein: 3.51,
+                    kcal: 158.0,
+                }),
+                servings: vec![("GRM".into(), 57.0), ("PANCAKE".into(), 1.0)],
+                barcode: Some("709481000201".into()),
+                ..Default::default()
+            }]]
+        );
+    }
+
+    #[test]
+    fn test_import_details() {
+        let index = Index::import(Path::new("tests/testdata/fdc/csv")).unwrap();
+        let search = OfflineSearch {
+            index: &index,
+            page_size: 1,
+        };
+        let hit = search.search("pancake", 1).unwrap().hits.remove(0);
+        // All nutrients are kept, not only those used to build the food.
+        assert_eq!(
+            hit.details.nutrients.last(),
+            Some(&("Nutrient 1093".into(), 491.0, "".into()))
+        );
+    }
+
+    #[test]
+    fn test_page_size_zero() {
+        let index = Index::import(Path::new("tests/testdata/fdc/foundation.json")).unwrap();
+        // A page size of 0 is treated as 1, rather than dividing by 0.
+        let pages = search(&index, "potato", 0);
+        assert_eq!(pages.len(), index.len());
+        assert_eq!(pages, search(&index, "potato", 1));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut index = Index::import(Path::new("tests/testdata/fdc/foundation.json")).unwrap();
+        index.merge(Index::import(Path::new("tests/testdata/fdc/csv")).unwrap());
+        assert_eq!(index.len(), 5);
+        // Importing the same data again replaces existing foods.
+        index.merge(Index::import(Path::new("tests/testdata/fdc/csv")).unwrap());
+        assert_eq!(index.len(), 5);
+    }
+}
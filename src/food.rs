@@ -1,5 +1,6 @@
 use crate::serving::Serving;
-use crate::{nutrients::Nutrients, Data};
+use crate::{check, nutrients::Nutrients, Data, Database};
+use std::path::Path;
 
 use anyhow::{bail, Context, Result};
 use ini::{Ini, WriteOption};
@@ -41,6 +42,15 @@ pub struct Food {
     // For example, [("g", 100.0), ("cups", 0.5)] means that
     // either 100g or 0.5cups equates to one serving.
     pub servings: Vec<(String, f32)>,
+
+    // The UPC/EAN barcode of a packaged food, if known.
+    pub barcode: Option<String>,
+
+    // Other keys that refer to this food, e.g. "pb" for peanut_butter.
+    pub aliases: Vec<String>,
+
+    // The ml of water or other fluid that one serving contributes, e.g. for drinks.
+    pub fluid: Option<f32>,
 }
 
 impl Food {
@@ -52,49 +62,133 @@ impl Food {
         }
     }
 
-    // Compute the nutrients in a serving of this food.
+    // Compute how many servings of this food the given serving is.
     // Returns an error if the serving unit is not defined for this food.
-    pub fn serve(&self, s: &Serving) -> Result<Nutrients> {
-        let portion = if let Some(unit) = &s.unit {
-            let mut matched = self.servings.iter().filter(|(u, _)| u.starts_with(unit));
-            let Some(first) = matched.next() else {
-                let units = self
-                    .servings
-                    .iter()
-                    .cloned()
-                    .map(|(unit, _)| unit)
-                    .collect::<Vec<_>>();
-                bail!(
-                    "Unknown serving unit {unit}, expected one of: {}",
-                    units.join(", ")
-                );
-            };
-
-            if let Some(next) = matched.next() {
-                bail!(
-                    "Serving unit '{unit}' ambiguous between '{}' and '{}'",
-                    first.0,
-                    next.0
-                );
-            }
-
-            let (_, size) = first;
-            s.size / *size
-        } else {
-            s.size
+    pub fn portion(&self, s: &Serving) -> Result<f32> {
+        let Some(unit) = &s.unit else {
+            return Ok(s.size);
         };
+        let mut matched = self.servings.iter().filter(|(u, _)| u.starts_with(unit));
+        let Some(first) = matched.next() else {
+            let units = self
+                .servings
+                .iter()
+                .cloned()
+                .map(|(unit, _)| unit)
+                .collect::<Vec<_>>();
+            bail!(
+                "Unknown serving unit {unit}, expected one of: {}",
+                units.join(", ")
+            );
+        };
+
+        if let Some(next) = matched.next() {
+            bail!(
+                "Serving unit '{unit}' ambiguous between '{}' and '{}'",
+                first.0,
+                next.0
+            );
+        }
+
+        let (_, size) = first;
+        Ok(s.size / *size)
+    }
 
+    // Compute the nutrients in a serving of this food.
+    // Returns an error if the serving unit is not defined for this food.
+    pub fn serve(&self, s: &Serving) -> Result<Nutrients> {
+        let portion = self.portion(s)?;
         match &self.spec {
             FoodSpec::Nutrients(n) => Ok(*n * portion),
             FoodSpec::Ingredients(ingredients) => {
                 let mut res = Nutrients::default();
                 for i in ingredients {
-                    res = res + i.food.serve(&(i.serving.clone() * portion))?;
+                    res += i.food.serve(&(i.serving.clone() * portion))?;
                 }
                 Ok(res)
             }
         }
     }
+
+    // Compute the ml of fluid in a serving of this food.
+    // A recipe without fluid of its own has the fluid of its ingredients.
+    pub fn fluid(&self, s: &Serving) -> Result<f32> {
+        let portion = self.portion(s)?;
+        if let Some(fluid) = self.fluid {
+            return Ok(fluid * portion);
+        }
+        match &self.spec {
+            FoodSpec::Nutrients(_) => Ok(0.0),
+            FoodSpec::Ingredients(ingredients) => {
+                let mut res = 0.0;
+                for i in ingredients {
+                    res += i.food.fluid(&(i.serving.clone() * portion))?;
+                }
+                Ok(res)
+            }
+        }
+    }
+
+    // Change what one serving of this food is, scaling the nutrients to match.
+    // For example, if one serving is 100g, resizing to 30g scales everything by 0.3.
+    pub fn resize(&mut self, s: &Serving) -> Result<()> {
+        let portion = self.portion(s)?;
+        match &mut self.spec {
+            FoodSpec::Nutrients(n) => *n = *n * portion,
+            FoodSpec::Ingredients(ingredients) => {
+                for i in ingredients {
+                    i.serving.size *= portion;
+                }
+            }
+        }
+        for (_, size) in &mut self.servings {
+            *size *= portion;
+        }
+        if let Some(fluid) = &mut self.fluid {
+            *fluid *= portion;
+        }
+        Ok(())
+    }
+}
+
+// Parse an amount of fluid as ml, such as "250 ml", "0.5 l" or "250".
+pub(crate) fn parse_fluid(s: &str) -> Result<f32> {
+    let serving: Serving = s.parse()?;
+    match (&serving.unit, serving.ml()) {
+        (None, _) => Ok(serving.size),
+        (Some(_), Some(ml)) => Ok(ml),
+        (Some(unit), None) => bail!("Fluid must be a volume such as 250 ml, not {unit}"),
+    }
+}
+
+#[test]
+fn test_food_fluid() {
+    let milk = Food {
+        fluid: Some(250.0),
+        servings: vec![("ml".into(), 250.0)],
+        ..Default::default()
+    };
+    assert_eq!(milk.fluid(&"500ml".parse().unwrap()).unwrap(), 500.0);
+    let oats = Food::default();
+    assert_eq!(oats.fluid(&Serving::default()).unwrap(), 0.0);
+    let porridge = Food {
+        spec: FoodSpec::Ingredients(vec![
+            Ingredient {
+                key: "milk".into(),
+                serving: "1".parse().unwrap(),
+                food: milk,
+            },
+            Ingredient {
+                key: "oats".into(),
+                serving: "1".parse().unwrap(),
+                food: oats,
+            },
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(porridge.fluid(&"0.5".parse().unwrap()).unwrap(), 125.0);
+    assert_eq!(parse_fluid("0.5 l").unwrap(), 500.0);
+    assert!(parse_fluid("5 g").is_err());
 }
 
 #[test]
@@ -108,6 +202,7 @@ fn test_food_serve() {
             kcal: 120.0,
         }),
         servings: vec![("g".into(), 100.0), ("cups".into(), 0.5)],
+        ..Default::default()
     };
     let serve = |size, unit: Option<&str>| {
         food.serve(&Serving {
@@ -153,9 +248,66 @@ fn test_food_serve() {
     );
 }
 
+#[test]
+fn test_food_resize() {
+    let mut food = Food {
+        spec: FoodSpec::Nutrients(Nutrients {
+            carb: 12.0,
+            fat: 3.0,
+            protein: 8.0,
+            kcal: 120.0,
+        }),
+        servings: vec![("g".into(), 100.0), ("cups".into(), 0.5)],
+        ..Default::default()
+    };
+    food.resize(&"1c".parse().unwrap()).unwrap();
+    assert_eq!(
+        food.nutrients(),
+        Nutrients {
+            carb: 24.0,
+            fat: 6.0,
+            protein: 16.0,
+            kcal: 240.0,
+        }
+    );
+    assert_eq!(
+        food.servings,
+        vec![("g".into(), 200.0), ("cups".into(), 1.0)]
+    );
+}
+
 impl Data for Food {
     type Key = str;
     const DIR: &str = "food";
+    const GUIDE: &str = "\
+##! A food has a name, and the nutrients of one serving:
+##!   name = Oats
+##!   [nutrients]
+##!   carb = 68.7
+##!   fat = 5.89
+##!   protein = 13.5
+##!   kcal = 382
+##! If kcal is 0 or missing, it is computed from the other nutrients.
+##!
+##! A recipe has ingredients instead of nutrients, with the key and serving
+##! of each food in the recipe:
+##!   [ingredients]
+##!   oats = 0.5 cups
+##!   banana = 1
+##!
+##! Servings give the size of one serving in other units,
+##! so it can be eaten as e.g. `nosh eat oats 50g`:
+##!   [servings]
+##!   cups = 0.5
+##!   g = 100
+##!
+##! Optionally, other keys to find the food by, and its barcode:
+##!   aliases = porridge, oatmeal
+##!   barcode = 0123456789
+##!
+##! Drinks and other foods with water can give the fluid in a serving:
+##!   fluid = 250 ml
+";
 
     fn path(key: &str) -> std::path::PathBuf {
         [Self::DIR, key]
@@ -178,6 +330,21 @@ impl Data for Food {
             bail!("Missing name");
         };
 
+        food.barcode = ini.general_section().get("barcode").map(str::to_string);
+
+        if let Some(fluid) = ini.general_section().get("fluid") {
+            food.fluid = Some(parse_fluid(fluid)?);
+        }
+
+        if let Some(aliases) = ini.general_section().get("aliases") {
+            food.aliases = aliases
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+
         if let Some(servings) = ini.section(Some("servings")) {
             for (k, v) in servings.iter() {
                 log::trace!("Parsing serving: {k} = {v}");
@@ -192,12 +359,13 @@ impl Data for Food {
             (None, None) => bail!("Must specify one of [nutrients] or [ingredients]"),
             (Some(n), None) => {
                 log::trace!("Parsing nutrients");
-                let mut nutrients = Nutrients::default();
-                nutrients.kcal = n.get("kcal").unwrap_or("0").parse()?;
-                nutrients.carb = n.get("carb").unwrap_or("0").parse()?;
-                nutrients.fat = n.get("fat").unwrap_or("0").parse()?;
-                nutrients.protein = n.get("protein").unwrap_or("0").parse()?;
-                food.spec = FoodSpec::Nutrients(nutrients);
+                let nutrients = Nutrients {
+                    kcal: n.get("kcal").unwrap_or("0").parse()?,
+                    carb: n.get("carb").unwrap_or("0").parse()?,
+                    fat: n.get("fat").unwrap_or("0").parse()?,
+                    protein: n.get("protein").unwrap_or("0").parse()?,
+                };
+                food.spec = FoodSpec::Nutrients(nutrients.maybe_compute_kcal());
             }
             (None, Some(i)) => {
                 log::trace!("Parsing ingredients");
@@ -221,6 +389,17 @@ impl Data for Food {
         log::debug!("Saving {self:?}");
         let mut ini = Ini::new();
         ini.general_section_mut().insert("name", &self.name);
+        if let Some(barcode) = &self.barcode {
+            ini.general_section_mut().insert("barcode", barcode);
+        }
+        if !self.aliases.is_empty() {
+            ini.general_section_mut()
+                .insert("aliases", self.aliases.join(", "));
+        }
+        if let Some(fluid) = self.fluid {
+            ini.general_section_mut()
+                .insert("fluid", format!("{fluid} ml"));
+        }
         match &self.spec {
             FoodSpec::Nutrients(n) => {
                 let mut sec = ini.with_section(Some("nutrients"));
@@ -249,6 +428,15 @@ impl Data for Food {
                 ..Default::default()
             },
         )?;
+        // Ini skips empty sections, but a recipe without ingredients yet
+        // still needs the section to load as a recipe.
+        if matches!(&self.spec, FoodSpec::Ingredients(i) if i.is_empty()) {
+            writeln!(w, "\n[ingredients]")?;
+        }
         Ok(())
     }
+
+    fn check(data: &Database, path: &Path, text: &str) -> Vec<check::Problem> {
+        check::food(data, path, text)
+    }
 }
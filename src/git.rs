@@ -0,0 +1,249 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Records changes to the data directory as commits in a git repository.
+// This uses the git CLI, so the user's own git configuration applies.
+#[derive(Debug)]
+pub struct Git {
+    dir: PathBuf,
+}
+
+// The trailer that marks commits made by nosh, with the kind of change,
+// such as "Nosh-Change: eat". Only these commits are undone.
+const TRAILER: &str = "Nosh-Change";
+
+// A commit made by nosh.
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Commit {
+    pub id: String,      // abbreviated hash
+    pub date: String,    // "2024-07-01 08:30"
+    pub message: String, // "eat oats 0.5 c on 2024-07-01"
+}
+
+impl Git {
+    // Use the repository at dir, if there is one.
+    pub fn open(dir: &Path) -> Option<Git> {
+        dir.join(".git").exists().then(|| Git { dir: dir.into() })
+    }
+
+    // Create a repository at dir.
+    pub fn init(dir: &Path) -> Result<Git> {
+        let git = Git { dir: dir.into() };
+        git.run(&["init", "--quiet"])?;
+        Ok(git)
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C").arg(&self.dir);
+        cmd
+    }
+
+    // Run git, returning stdout.
+    pub(crate) fn run(&self, args: &[&str]) -> Result<String> {
+        let mut cmd = self.command();
+        cmd.args(args);
+        log::debug!("Running {cmd:?}");
+        let out = cmd.output().with_context(|| format!("Run {cmd:?}"))?;
+        if !out.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into())
+    }
+
+    // Commit the current state of the given paths, relative to the repository.
+    // Returns false if there was nothing to commit.
+    pub fn commit(&self, paths: &[PathBuf], message: &str) -> Result<bool> {
+        for path in paths {
+            let path = path
+                .to_str()
+                .with_context(|| format!("Non UTF-8 path: {path:?}"))?;
+            if self.dir.join(path).exists() {
+                self.run(&["add", "--", path])?;
+            } else {
+                self.run(&["rm", "--cached", "--quiet", "--ignore-unmatch", "--", path])?;
+            }
+        }
+        let staged = self
+            .command()
+            .args(["diff", "--cached", "--quiet"])
+            .status()?;
+        if staged.success() {
+            log::debug!("Nothing to commit for {message:?}");
+            return Ok(false);
+        }
+        let kind = message.split_whitespace().next().unwrap_or_default();
+        let trailer = format!("{TRAILER}: {kind}");
+        self.run(&[
+            "commit",
+            "--quiet",
+            "--message",
+            message,
+            "--message",
+            &trailer,
+        ])?;
+        Ok(true)
+    }
+
+    // Return the most recent commits, newest first.
+    pub fn log(&self, n: usize) -> Result<Vec<Commit>> {
+        let out = self.run(&[
+            "log",
+            &format!("--max-count={n}"),
+            "--date=format:%Y-%m-%d %H:%M",
+            "--format=%h%x09%ad%x09%s",
+        ])?;
+        Ok(out
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, '\t');
+                Some(Commit {
+                    id: parts.next()?.into(),
+                    date: parts.next()?.into(),
+                    message: parts.next()?.into(),
+                })
+            })
+            .collect())
+    }
+
+    // Revert the most recent commit made by nosh that hasn't already been reverted.
+    // Reverts are themselves commits, so repeated undos step further back
+    // in history instead of undoing the previous undo.
+    // Commits made by the user, without the nosh trailer, are never reverted.
+    // Returns the reverted commit, or None if there is nothing to undo.
+    pub fn undo(&self) -> Result<Option<Commit>> {
+        let out = self.run(&["log", "--format=%H%x00%s%x00%b%x1e"])?;
+        let mut reverted = vec![];
+        for record in out.split('\x1e') {
+            let mut parts = record.trim_start().splitn(3, '\0');
+            let (Some(hash), Some(subject), Some(body)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            if let Some(target) = body
+                .lines()
+                .find_map(|l| l.strip_prefix("This reverts commit "))
+            {
+                reverted.push(target.trim_end_matches('.').to_string());
+                continue;
+            }
+            if reverted.iter().any(|r| r == hash) {
+                continue;
+            }
+            let trailer = format!("{TRAILER}:");
+            if !body.lines().any(|l| l.starts_with(&trailer)) {
+                log::debug!("Not undoing {hash}, which wasn't made by nosh");
+                continue;
+            }
+            self.run(&["revert", "--no-edit", hash])?;
+            let date = self.run(&[
+                "log",
+                "-1",
+                "--date=format:%Y-%m-%d %H:%M",
+                "--format=%ad",
+                hash,
+            ])?;
+            return Ok(Some(Commit {
+                id: hash[..7.min(hash.len())].into(),
+                date: date.trim().into(),
+                message: subject.into(),
+            }));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    // Create a repository with an identity, so commits work without global config.
+    fn init(dir: &Path) -> Git {
+        let git = Git::init(dir).unwrap();
+        git.run(&["config", "user.name", "nosh"]).unwrap();
+        git.run(&["config", "user.email", "nosh@example.com"])
+            .unwrap();
+        git
+    }
+
+    fn messages(git: &Git) -> Vec<String> {
+        git.log(10)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_commit() {
+        let _ = env_logger::try_init();
+        let tmp = tempfile::tempdir().unwrap();
+        let git = init(tmp.path());
+
+        fs::write(tmp.path().join("a.txt"), "1").unwrap();
+        assert!(git.commit(&["a.txt".into()], "add a").unwrap());
+        // Nothing changed.
+        assert!(!git.commit(&["a.txt".into()], "add a again").unwrap());
+
+        fs::remove_file(tmp.path().join("a.txt")).unwrap();
+        assert!(git.commit(&["a.txt".into()], "remove a").unwrap());
+        // Removing a file that was never committed is fine.
+        assert!(!git.commit(&["b.txt".into()], "remove b").unwrap());
+
+        assert_eq!(messages(&git), vec!["remove a", "add a"]);
+    }
+
+    #[test]
+    fn test_undo() {
+        let _ = env_logger::try_init();
+        let tmp = tempfile::tempdir().unwrap();
+        let git = init(tmp.path());
+        let path = tmp.path().join("a.txt");
+        let read = || fs::read_to_string(&path).unwrap_or_default();
+
+        for content in ["1", "2", "3"] {
+            fs::write(&path, content).unwrap();
+            git.commit(&["a.txt".into()], &format!("write {content}"))
+                .unwrap();
+        }
+
+        assert_eq!(git.undo().unwrap().unwrap().message, "write 3");
+        assert_eq!(read(), "2");
+        assert_eq!(git.undo().unwrap().unwrap().message, "write 2");
+        assert_eq!(read(), "1");
+        assert_eq!(git.undo().unwrap().unwrap().message, "write 1");
+        assert_eq!(read(), "");
+        assert!(git.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undo_skips_user_commits() {
+        let _ = env_logger::try_init();
+        let tmp = tempfile::tempdir().unwrap();
+        let git = init(tmp.path());
+
+        fs::write(tmp.path().join("a.txt"), "1").unwrap();
+        git.commit(&["a.txt".into()], "eat 1").unwrap();
+        fs::write(tmp.path().join("b.txt"), "mine").unwrap();
+        git.run(&["add", "b.txt"]).unwrap();
+        git.run(&["commit", "--quiet", "--message", "my own change"])
+            .unwrap();
+
+        assert_eq!(git.undo().unwrap().unwrap().message, "eat 1");
+        assert!(!tmp.path().join("a.txt").exists());
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("b.txt")).unwrap(),
+            "mine"
+        );
+        assert!(git.undo().unwrap().is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{food::parse_fluid, Data, Food, Nutrients, DAILY_VALUES};
+use anyhow::{bail, Context, Result};
+use ini::{Ini, WriteOption};
+
+// The fluid to drink per day if no goal is set, in ml.
+pub const DEFAULT_FLUID: f32 = 2000.0;
+
+// Goals are daily targets, kept in one file at the root of the database.
+// For example:
+// ```
+// fluid = 2500 ml
+// kcal = 2200
+// protein = 140
+// ```
+// Nutrient goals replace the reference daily values used for %DV.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Goals {
+    // The ml of fluid to drink per day.
+    pub fluid: Option<f32>,
+    // Nutrients to eat per day, by name, such as ("protein", 140.0).
+    pub nutrients: Vec<(String, f32)>,
+}
+
+impl Goals {
+    // The ml of fluid to drink per day, or the default if no goal is set.
+    pub fn fluid(&self) -> f32 {
+        self.fluid.unwrap_or(DEFAULT_FLUID)
+    }
+
+    // The daily value of each nutrient: its goal if set, or the reference value.
+    pub fn daily_values(&self) -> Nutrients {
+        let mut res = Nutrients::daily_values();
+        for (name, value) in &self.nutrients {
+            if let Some(n) = res.get_mut(name) {
+                *n = *value;
+            }
+        }
+        res
+    }
+
+    // Set a goal by name, such as "fluid" to "2.5l" or "protein" to "140".
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        if name == "fluid" {
+            self.fluid = Some(parse_fluid(value)?);
+            return Ok(());
+        }
+        if DAILY_VALUES.iter().all(|(n, _)| *n != name) {
+            let names: Vec<_> = DAILY_VALUES.iter().map(|(n, _)| *n).collect();
+            bail!(
+                "Unknown goal {name:?}, expected fluid or one of {}",
+                names.join(", ")
+            );
+        }
+        let value: f32 = value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid {name} goal {value:?}"))?;
+        match self.nutrients.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.nutrients.push((name.into(), value)),
+        }
+        Ok(())
+    }
+}
+
+impl Data for Goals {
+    type Key = ();
+    const DIR: &str = "goals";
+    const GUIDE: &str = "\
+##! Each line is a daily goal. Fluid is a volume, defaulting to 2000 ml:
+##!   fluid = 2500 ml
+##!
+##! Nutrient goals are used for %DV instead of the FDA reference values,
+##! which are 275 carb, 78 fat, 50 protein and 2000 kcal:
+##!   kcal = 2200
+##!   protein = 140
+";
+
+    fn path(_key: &()) -> std::path::PathBuf {
+        std::path::PathBuf::from(Self::DIR).with_extension("txt")
+    }
+
+    fn load(
+        mut r: impl std::io::BufRead,
+        _load_food: impl FnMut(&str) -> Result<Option<Food>>,
+    ) -> Result<Self> {
+        let ini = Ini::read_from(&mut r)?;
+        log::trace!("Parsing: {ini:?}");
+        let mut res = Self::default();
+        for (k, v) in ini.general_section() {
+            res.set(k, v).with_context(|| format!("Goal {k}"))?;
+        }
+        Ok(res)
+    }
+
+    fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
+        let mut ini = Ini::new();
+        let mut sec = ini.with_general_section();
+        if let Some(fluid) = self.fluid {
+            sec.set("fluid", format!("{fluid} ml"));
+        }
+        for (name, value) in &self.nutrients {
+            sec.set(name, value.to_string());
+        }
+        ini.write_to_opt(
+            w,
+            WriteOption {
+                line_separator: ini::LineSeparator::CR,
+                kv_separator: " = ",
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_goals_round_trip() {
+        let load = |s: &str| Goals::load(s.as_bytes(), |_| Ok(None));
+        assert_eq!(load("").unwrap().fluid(), DEFAULT_FLUID);
+
+        let goals = load("fluid = 2.5 l\n").unwrap();
+        assert_eq!(goals.fluid(), 2500.0);
+        let mut buf = vec![];
+        goals.save(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "fluid = 2500 ml\n");
+
+        assert!(load("fluid = 2 kg\n").is_err());
+        assert!(load("sleep = 8 h\n").is_err());
+        assert!(load("kcal = lots\n").is_err());
+    }
+
+    #[test]
+    fn test_goals_daily_values() {
+        let goals = Goals::load("protein = 140\n".as_bytes(), |_| Ok(None)).unwrap();
+        let dv = goals.daily_values();
+        assert_eq!(dv.protein, 140.0);
+        assert_eq!(dv.kcal, 2000.0);
+        let mut buf = vec![];
+        goals.save(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "protein = 140\n");
+    }
+}
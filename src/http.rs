@@ -0,0 +1,250 @@
+use anyhow::{Context, Result};
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+// An HTTP client shared by the search providers.
+// Failed requests are retried with exponential backoff, and successful
+// responses may be cached on disk so repeating a request does not hit the network.
+pub struct Client {
+    client: reqwest::blocking::Client,
+
+    // How many times to retry a request that timed out or was rate limited.
+    pub retries: u32,
+
+    // How long to wait before the first retry. This doubles after each attempt.
+    // If the server sends a Retry-After header, that is used instead.
+    pub backoff: Duration,
+
+    // Give up instead of waiting longer than this for a retry.
+    pub max_wait: Duration,
+
+    // Where to cache responses. If None, responses are not cached.
+    pub cache_dir: Option<PathBuf>,
+
+    // How long a cached response is used before it is fetched again.
+    pub cache_age: Duration,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30)).expect("Failed to build HTTP client")
+    }
+}
+
+impl Client {
+    // Create a client that will abort requests that take longer than timeout.
+    pub fn new(timeout: Duration) -> Result<Client> {
+        Ok(Client {
+            client: reqwest::blocking::Client::builder()
+                .timeout(timeout)
+                .build()?,
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            max_wait: Duration::from_secs(60),
+            cache_dir: None,
+            cache_age: Duration::from_secs(24 * 60 * 60),
+        })
+    }
+
+    // A client with default settings, used by providers that aren't given one.
+    pub fn shared() -> &'static Client {
+        static CLIENT: OnceLock<Client> = OnceLock::new();
+        CLIENT.get_or_init(Client::default)
+    }
+
+    // Start building a GET request.
+    pub fn get(&self, url: &str) -> reqwest::blocking::RequestBuilder {
+        self.client.get(url)
+    }
+
+    // Send a request and return the response body.
+    // Responses are cached by URL, so headers should not affect the response.
+    pub fn fetch(&self, req: reqwest::blocking::Request) -> Result<String> {
+        let url = req.url().to_string();
+        if let Some(body) = self.load_cache(&url) {
+            log::debug!("Using cached response for {url}");
+            return Ok(body);
+        }
+
+        let mut wait = self.backoff;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let copy = req.try_clone().context("Request cannot be retried")?;
+            log::debug!("Sending request (attempt {attempt}): {copy:?}");
+
+            let err = match self.client.execute(copy) {
+                Ok(res) if res.status().is_success() => {
+                    let body = res.text()?;
+                    self.save_cache(&url, &body);
+                    return Ok(body);
+                }
+                Ok(res) if Self::should_retry(res.status()) => {
+                    if let Some(after) = Self::retry_after(&res) {
+                        wait = after;
+                    }
+                    res.error_for_status().unwrap_err()
+                }
+                Ok(res) => return Err(res.error_for_status().unwrap_err().into()),
+                Err(err) if err.is_timeout() || err.is_connect() => err,
+                Err(err) => return Err(err.into()),
+            };
+
+            if attempt > self.retries {
+                return Err(err).context(format!("Giving up after {attempt} attempts"));
+            }
+            if wait > self.max_wait {
+                return Err(err).context(format!("Server asked to retry after {wait:?}"));
+            }
+            log::warn!("Request failed, retrying in {wait:?}: {err}");
+            std::thread::sleep(wait);
+            wait *= 2;
+        }
+    }
+
+    fn should_retry(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    // Parse a Retry-After header, which is either a number of seconds or a date.
+    fn retry_after(res: &reqwest::blocking::Response) -> Option<Duration> {
+        let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?;
+        if let Ok(secs) = value.parse() {
+            return Some(Duration::from_secs(secs));
+        }
+        let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+        Some(
+            SystemTime::from(date)
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        )
+    }
+
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        // FNV-1a, which unlike std's hasher is stable across releases.
+        let hash = url.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        Some(self.cache_dir.as_ref()?.join(format!("{hash:016x}.json")))
+    }
+
+    fn load_cache(&self, url: &str) -> Option<String> {
+        let path = self.cache_path(url)?;
+        let age = std::fs::metadata(&path)
+            .ok()?
+            .modified()
+            .ok()?
+            .elapsed()
+            .ok()?;
+        if age > self.cache_age {
+            log::debug!("Cached response {path:?} is stale");
+            return None;
+        }
+        std::fs::read_to_string(path).ok()
+    }
+
+    fn save_cache(&self, url: &str, body: &str) {
+        let Some(path) = self.cache_path(url) else {
+            return;
+        };
+        log::debug!("Caching response for {url} at {path:?}");
+        let res = std::fs::create_dir_all(path.parent().unwrap_or(&path))
+            .and_then(|_| std::fs::write(&path, body));
+        if let Err(err) = res {
+            // The cache is only an optimization, so this isn't fatal.
+            log::warn!("Failed to write {path:?}: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httptest::{cycle, matchers::*, responders::*, Expectation, Server};
+
+    fn client() -> Client {
+        let mut client = Client::new(Duration::from_secs(5)).unwrap();
+        client.backoff = Duration::ZERO;
+        client
+    }
+
+    #[test]
+    fn test_retry() {
+        let _ = env_logger::try_init();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/test"))
+                .times(3)
+                .respond_with(cycle![
+                    status_code(429).insert_header("Retry-After", "0"),
+                    status_code(503),
+                    status_code(200).body("ok"),
+                ]),
+        );
+        let client = client();
+        let req = client.get(&server.url_str("/test")).build().unwrap();
+        assert_eq!(client.fetch(req).unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_retry_give_up() {
+        let _ = env_logger::try_init();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/test"))
+                .times(4)
+                .respond_with(status_code(503)),
+        );
+        let client = client();
+        let req = client.get(&server.url_str("/test")).build().unwrap();
+        assert!(client.fetch(req).is_err());
+    }
+
+    #[test]
+    fn test_retry_after_too_long() {
+        let _ = env_logger::try_init();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/test"))
+                .times(1)
+                .respond_with(status_code(429).insert_header("Retry-After", "3600")),
+        );
+        let client = client();
+        let req = client.get(&server.url_str("/test")).build().unwrap();
+        assert!(client.fetch(req).is_err());
+    }
+
+    #[test]
+    fn test_cache() {
+        let _ = env_logger::try_init();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/test"))
+                .times(2)
+                .respond_with(cycle![
+                    status_code(200).body("page1"),
+                    status_code(200).body("page2"),
+                ]),
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        let mut client = client();
+        client.cache_dir = Some(tmp.path().into());
+
+        let fetch = |page: &str| {
+            let req = client
+                .get(&server.url_str("/test"))
+                .query(&[("page", page)])
+                .build()
+                .unwrap();
+            client.fetch(req).unwrap()
+        };
+
+        // Only the first request for each page hits the server.
+        assert_eq!(fetch("1"), "page1");
+        assert_eq!(fetch("2"), "page2");
+        assert_eq!(fetch("1"), "page1");
+        assert_eq!(fetch("2"), "page2");
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{Data, Food, Nutrients, Serving};
-use anyhow::{Context, Result};
-use chrono::{Datelike, NaiveDate};
+use crate::{check, Data, Database, Food, FoodSpec, Nutrients, Serving};
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
 use ini::{Ini, WriteOption};
+use std::path::Path;
 
 #[derive(Debug, Default)]
 #[cfg_attr(test, derive(PartialEq))]
@@ -9,24 +10,110 @@ pub struct JournalEntry {
     pub key: String,
     pub serving: Serving,
     pub food: Food,
+    // If true, the food is saved in the journal itself,
+    // rather than referring to a file in the food database.
+    pub inline: bool,
+    // The time of day the food was eaten, if known.
+    pub time: Option<NaiveTime>,
+    // Free text about the entry, such as "weighed after cooking".
+    pub note: Option<String>,
+}
+
+impl JournalEntry {
+    // Set the time of the entry to the current time, to the minute.
+    pub fn eaten_now(mut self) -> Self {
+        let now = chrono::Local::now().time();
+        self.time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0);
+        self
+    }
+
+    // The value of the entry's line: "serving | time | note".
+    // Time and note are only written if set, so journals without them are unchanged.
+    fn value(&self) -> String {
+        let serving = &self.serving;
+        let time = self.time.map(|t| t.format(TIME_FORMAT));
+        match (time, &self.note) {
+            (None, None) => serving.to_string(),
+            (Some(time), None) => format!("{serving} | {time}"),
+            (Some(time), Some(note)) => format!("{serving} | {time} | {note}"),
+            (None, Some(note)) => format!("{serving} | | {note}"),
+        }
+    }
+}
+
+// The format of the time an entry was eaten, in journals and when shown.
+pub const TIME_FORMAT: &str = "%H:%M";
+
+// Split the value of a journal line into its serving, time and note.
+// The time and note are optional, and empty parts are None.
+pub(crate) fn split_value(value: &str) -> (&str, Option<&str>, Option<&str>) {
+    let mut parts = value.splitn(3, '|').map(str::trim);
+    let serving = parts.next().unwrap_or_default();
+    let mut next = || parts.next().filter(|s| !s.is_empty());
+    let time = next();
+    (serving, time, next())
+}
+
+// Parse a time of day, such as 08:30.
+pub fn parse_time(s: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(s, TIME_FORMAT)
+        .with_context(|| format!("Invalid time {s:?}, expected HH:MM"))
 }
 
 // Journal is a record of food consumed during a day.
 // It is a list of "food = serving" lines.
 // The serving is optional and defaults to 1.
+// It may be followed by the time the food was eaten, and a note, separated by "|".
 // For example:
 // ```
-// oats = 0.5 cup
-// banana = 1
+// oats = 0.5 cup | 08:15
+// banana = 1 | 08:15 | a bit green
 // berries
 // ```
+// A food may also be defined inline, in a section named after its key.
+// This keeps the journal loadable if the food is removed from the database,
+// and is how foods that were never in the database are quick-added.
+// ```
+// oats = 0.5 cups
+//
+// [oats]
+// name = Oats
+// carb = 68.7
+// fat = 5.89
+// protein = 13.5
+// kcal = 382
+// servings = 0.5 cups, 100 g
+// ```
 #[derive(Debug, Default)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Journal(pub Vec<JournalEntry>);
 
+// Help for editing journals and meals.
+pub(crate) const GUIDE: &str = "\
+##! Each line is the key of a food and a serving, which defaults to 1:
+##!   oats = 0.5 cups
+##!   banana = 2
+##!   berries
+##!
+##! A serving may be followed by the time it was eaten and a note:
+##!   oats = 0.5 cups | 08:15 | weighed dry
+##!   rice = 1 cup | | leftovers
+##!
+##! A food that isn't in the database can be defined in a section named
+##! after its key, with the nutrients of one serving:
+##!   [burger]
+##!   name = Restaurant burger
+##!   kcal = 650
+##!   protein = 30
+##! It may also give its servings and fluid, e.g. for water:
+##!   servings = 1 ml
+##!   fluid = 1 ml
+";
+
 impl Data for Journal {
     type Key = NaiveDate;
     const DIR: &str = "journal";
+    const GUIDE: &str = GUIDE;
 
     fn path(key: &NaiveDate) -> std::path::PathBuf {
         format!(
@@ -46,35 +133,185 @@ impl Data for Journal {
         let ini = Ini::read_from(&mut r)?;
         log::trace!("Parsing: {ini:?}");
         for (k, v) in ini.general_section() {
+            let (food, inline) = match ini.section(Some(k)) {
+                Some(section) => (
+                    load_inline(section).with_context(|| format!("Inline food {k}"))?,
+                    true,
+                ),
+                None => (
+                    load_food(k)?.with_context(|| format!("Food not found: {k}"))?,
+                    false,
+                ),
+            };
+            let (serving, time, note) = split_value(v);
             rows.push(JournalEntry {
                 key: k.into(),
-                serving: v.parse()?,
-                food: load_food(k)?.with_context(|| format!("Food not found: {k}"))?,
+                serving: serving.parse()?,
+                food,
+                inline,
+                time: time.map(parse_time).transpose()?,
+                note: note.map(str::to_string),
             })
         }
         Ok(Self(rows))
     }
 
     fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
-        let mut ini = Ini::new();
-        let mut sec = ini.with_general_section();
-        for JournalEntry { key, serving, .. } in &self.0 {
-            sec.add(key, serving.to_string());
+        save_entries(&self.0, w)
+    }
+
+    fn check(data: &Database, path: &Path, text: &str) -> Vec<check::Problem> {
+        check::journal(data, path, text)
+    }
+}
+
+// Save journal entries, as used by both journals and meals.
+pub(crate) fn save_entries(entries: &[JournalEntry], w: &mut impl std::io::Write) -> Result<()> {
+    let mut ini = Ini::new();
+    let mut sec = ini.with_general_section();
+    for entry in entries {
+        sec.add(&entry.key, entry.value());
+    }
+    for entry in entries.iter().filter(|e| e.inline) {
+        if ini.section(Some(&entry.key)).is_some() {
+            continue;
+        }
+        let food = &entry.food;
+        let n = food.nutrients();
+        let mut sec = ini.with_section(Some(&entry.key));
+        sec.set("name", &food.name)
+            .set("carb", n.carb.to_string())
+            .set("fat", n.fat.to_string())
+            .set("protein", n.protein.to_string())
+            .set("kcal", n.kcal.to_string());
+        if let Some(fluid) = food.fluid {
+            sec.set("fluid", format!("{fluid} ml"));
+        }
+        if !food.servings.is_empty() {
+            sec.set(
+                "servings",
+                food.servings
+                    .iter()
+                    .map(|(unit, size)| format!("{size} {unit}"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
         }
-        log::trace!("Writing: {ini:?}");
-        ini.write_to_opt(
-            w,
-            WriteOption {
-                line_separator: ini::LineSeparator::CR,
-                kv_separator: " = ",
-                ..Default::default()
-            },
-        )?;
-        Ok(())
     }
+    log::trace!("Writing: {ini:?}");
+    ini.write_to_opt(
+        w,
+        WriteOption {
+            line_separator: ini::LineSeparator::CR,
+            kv_separator: " = ",
+            ..Default::default()
+        },
+    )?;
+    Ok(())
+}
+
+// Parse a food defined in a section of a journal.
+fn load_inline(section: &ini::Properties) -> Result<Food> {
+    let get = |k| -> Result<f32> { Ok(section.get(k).unwrap_or("0").parse()?) };
+    let mut servings = vec![];
+    for s in section.get("servings").unwrap_or_default().split(',') {
+        let s = s.trim();
+        if s.is_empty() {
+            continue;
+        }
+        let serving: Serving = s.parse()?;
+        let Some(unit) = serving.unit else {
+            bail!("Serving has no unit: {s}");
+        };
+        servings.push((unit, serving.size));
+    }
+    Ok(Food {
+        name: section.get("name").context("Missing name")?.into(),
+        spec: FoodSpec::Nutrients(Nutrients {
+            carb: get("carb")?,
+            fat: get("fat")?,
+            protein: get("protein")?,
+            kcal: get("kcal")?,
+        }),
+        servings,
+        fluid: section
+            .get("fluid")
+            .map(crate::food::parse_fluid)
+            .transpose()?,
+        ..Default::default()
+    })
+}
+
+// The fluid of a food once it's saved in a journal, keeping that of a recipe's ingredients.
+fn inline_fluid(food: &Food) -> Option<f32> {
+    food.fluid
+        .or_else(|| food.fluid(&Serving::default()).ok().filter(|&f| f > 0.0))
+}
+
+// Replace a food's ingredients with their nutrients, so it can be saved in a journal.
+fn snapshot(food: &mut Food) {
+    food.fluid = inline_fluid(food);
+    food.spec = FoodSpec::Nutrients(food.nutrients());
+    food.aliases.clear();
+}
+
+// Whether two foods would be saved as the same section of a journal.
+fn same_inline(a: &Food, b: &Food) -> bool {
+    a.name == b.name
+        && a.nutrients() == b.nutrients()
+        && a.servings == b.servings
+        && inline_fluid(a) == inline_fluid(b)
 }
 
 impl Journal {
+    // Copy the food with the given key into the journal, so it no longer
+    // depends on the food database. Returns true if any entry was changed.
+    pub fn inline(&mut self, key: &str) -> bool {
+        let mut changed = false;
+        for entry in self.0.iter_mut().filter(|e| e.key == key && !e.inline) {
+            snapshot(&mut entry.food);
+            entry.inline = true;
+            changed = true;
+        }
+        changed
+    }
+
+    // Append entries, such as those copied from another journal or a meal.
+    // If an entry defines its food inline, other entries of that food are
+    // inlined too, as a journal can only define each key once.
+    // If the journal already defines a different food with the same key, such as
+    // an older snapshot, the entry is inlined with a new key, like "oats_2".
+    pub fn extend(&mut self, entries: impl IntoIterator<Item = JournalEntry>) {
+        for mut entry in entries {
+            let same_key: Vec<_> = self.0.iter().filter(|e| e.key == entry.key).collect();
+            let clash = same_key.iter().any(|e| e.inline || entry.inline)
+                && !same_inline(&entry.food, &same_key[0].food);
+            if clash {
+                let base = entry.key.clone();
+                entry.key = (2..)
+                    .map(|i| format!("{base}_{i}"))
+                    .find(|k| self.0.iter().all(|e| &e.key != k))
+                    .unwrap_or(base);
+                snapshot(&mut entry.food);
+                entry.inline = true;
+            }
+            let key = entry.key.clone();
+            self.0.push(entry);
+            if self.0.iter().any(|e| e.key == key && e.inline) {
+                self.inline(&key);
+            }
+        }
+    }
+
+    // Compute the total ml of fluid in this journal.
+    pub fn fluid(&self) -> Result<f32> {
+        let mut res = 0.0;
+        for entry in &self.0 {
+            res += entry.food.fluid(&entry.serving)?;
+        }
+        Ok(res)
+    }
+
     // Compute the total nutrients of this journal.
     pub fn nutrients(&self) -> Result<Nutrients> {
         let mut res = Nutrients::default();
@@ -1,24 +1,64 @@
+pub mod activity;
+pub mod body;
+pub mod check;
 pub mod data;
+pub mod edit;
+pub mod fdc;
 pub mod food;
+pub mod git;
+pub mod goals;
+pub mod http;
 pub mod journal;
+pub mod meal;
+pub mod merge;
 pub mod nutrients;
+pub mod off;
+pub mod refs;
 pub mod search;
 pub mod serving;
+pub mod tdee;
+pub mod tui;
 
+pub use activity::*;
+pub use body::*;
 use chrono::NaiveDate;
 pub use data::*;
+pub use fdc::*;
 pub use food::*;
+pub use goals::*;
 pub use journal::*;
+pub use meal::*;
 pub use nutrients::*;
+pub use off::*;
 pub use search::*;
 pub use serving::*;
+pub use tdee::*;
 
 use anyhow::{anyhow, bail, Context, Result};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use git::Git;
+use std::cell::RefCell;
 use std::fs;
 use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
 
-pub const APP_NAME: &'static str = env!("CARGO_PKG_NAME");
+pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
+
+// Score how well a query matches a food's key or name, higher is better.
+// Returns None if neither matches.
+pub fn fuzzy_score(query: &str, key: &str, name: &str) -> Option<i64> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let score = [key, name]
+        .iter()
+        .filter_map(|s| matcher.fuzzy_match(s, query))
+        .max();
+    // Subsequence matching misses typos like "oast", so fall back to edit distance.
+    // These are scored below any subsequence match.
+    score.or_else(|| {
+        let dist = strsim::damerau_levenshtein(&query.to_lowercase(), &key.to_lowercase());
+        (dist <= 2 && dist < query.len()).then_some(-(dist as i64))
+    })
+}
 
 // Database provides access to the nosh "database".
 // Nosh stores all of it's data as text files using a particular directory structure:
@@ -37,15 +77,154 @@ pub const APP_NAME: &'static str = env!("CARGO_PKG_NAME");
 //       - 12/
 //         - 30.txt
 //         - 31.txt
+//
+//   - meal/
+//     - breakfast.txt
+//
+//   - fdc/
+//     - index.json (foods imported from Food Data Central, for offline search)
+//
+// If $root is a git repository, every change to a food or journal is committed.
 #[derive(Debug)]
 pub struct Database {
     dir: PathBuf,
+    git: Option<Git>,
+    // Paths changed so far, while in a transaction.
+    changed: RefCell<Option<Vec<PathBuf>>>,
+}
+
+// Commit changes that have already been written, warning if that fails,
+// such as when git has no user configured.
+// The warning is always printed, not logged, as `undo` can't revert the change.
+fn commit(git: &Git, paths: &[PathBuf], message: &str) {
+    if let Err(err) = git.commit(paths, message) {
+        eprintln!(
+            "Warning: saved, but failed to record {message:?} in history, \
+             so `nosh undo` won't revert it: {err:#}"
+        );
+    }
 }
 
 impl Database {
+    // The path of the offline search index, relative to the root directory.
+    const INDEX_PATH: &str = "fdc/index.json";
+
     // Create a new database at the given root directory.
     pub fn new(dir: impl Into<PathBuf>) -> Result<Database> {
-        Ok(Database { dir: dir.into() })
+        let dir = dir.into();
+        Ok(Database {
+            git: Git::open(&dir),
+            dir,
+            changed: RefCell::new(None),
+        })
+    }
+
+    // The root directory.
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    // The git repository that records changes, if the root directory is one.
+    pub fn git(&self) -> Option<&Git> {
+        self.git.as_ref()
+    }
+
+    // Run f, committing all of the changes it makes as a single commit.
+    // Outside of a transaction, each change is committed on its own with a generic message.
+    // Changes are committed even if f fails partway, so the files it wrote aren't left
+    // out of history. Failing to commit only prints a warning, as the files were written.
+    pub fn transaction<T>(&self, message: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let Some(git) = &self.git else {
+            return f();
+        };
+        if self.changed.borrow().is_some() {
+            // The outer transaction will commit these changes.
+            return f();
+        }
+        *self.changed.borrow_mut() = Some(vec![]);
+        let res = f();
+        let changed = self.changed.borrow_mut().take().unwrap_or_default();
+        if !changed.is_empty() {
+            commit(git, &changed, message);
+        }
+        res
+    }
+
+    // Record a change to a path, relative to the root.
+    fn record(&self, path: PathBuf, message: impl FnOnce() -> String) -> Result<()> {
+        let Some(git) = &self.git else {
+            return Ok(());
+        };
+        if let Some(changed) = self.changed.borrow_mut().as_mut() {
+            changed.push(path);
+            return Ok(());
+        }
+        commit(git, &[path], &message());
+        Ok(())
+    }
+
+    // Save an item, merging it into the existing file so comments and ordering are kept.
+    fn save<T: Data>(
+        &self,
+        key: &T::Key,
+        data: &T,
+        message: impl FnOnce() -> String,
+    ) -> Result<()> {
+        let mut buf = vec![];
+        data.save(&mut buf)?;
+        let new = String::from_utf8(buf)?;
+        let text = match self.load_text::<T>(key)? {
+            Some(old) => merge::merge(&old, &new),
+            None => new,
+        };
+        self.save_text::<T>(key, &text, message)
+    }
+
+    // Load an item, if its file exists.
+    fn load<T: Data>(&self, key: &T::Key) -> Result<Option<T>> {
+        let rel = T::path(key);
+        let path = self.dir.join(&rel);
+        log::debug!("Loading {path:?}");
+        let file = match std::fs::File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                bail!("Failed to open '{path:?}': {e}")
+            }
+        };
+        let reader = BufReader::new(file);
+        Ok(Some(
+            T::load(reader, |key| self.load_ingredient(key))
+                .with_context(|| format!("Load {rel:?}"))?,
+        ))
+    }
+
+    // Load the text of an item's file, if it exists.
+    pub fn load_text<T: Data>(&self, key: &T::Key) -> Result<Option<String>> {
+        let path = self.dir.join(T::path(key));
+        match fs::read_to_string(&path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => bail!("Failed to read '{path:?}': {e}"),
+        }
+    }
+
+    // Write the text of an item's file as is, such as after editing it.
+    // The text should be checked first, e.g. by loading it.
+    pub fn save_text<T: Data>(
+        &self,
+        key: &T::Key,
+        text: &str,
+        message: impl FnOnce() -> String,
+    ) -> Result<()> {
+        let rel = T::path(key);
+        let path = self.dir.join(&rel);
+        fs::create_dir_all(
+            path.parent()
+                .ok_or_else(|| anyhow!("No parent path: {path:?}"))?,
+        )?;
+        fs::write(&path, text).with_context(|| format!("Write {path:?}"))?;
+        self.record(rel, message)
     }
 
     // Return a list of food keys.
@@ -63,48 +242,515 @@ impl Database {
         }))
     }
 
-    pub fn save_food(&self, key: &str, data: &Food) -> Result<()> {
+    // Return the keys and names of foods matching a query, best match first.
+    // Both keys and names are fuzzy matched, so "pnut" finds peanut_butter.
+    pub fn find_food(&self, query: &str) -> Result<Vec<(String, String)>> {
+        let mut res = vec![];
+        // Skip foods that can't be listed, such as non UTF-8 names, rather than failing.
+        for key in self
+            .list_food()?
+            .filter_map(|k| k.map_err(|e| log::warn!("{e:?}")).ok())
+        {
+            let name = match self.load_food_shallow(&key) {
+                Ok(food) => food.name,
+                Err(err) => {
+                    log::warn!("Failed to load food '{key}': {err:?}");
+                    continue;
+                }
+            };
+            if let Some(score) = fuzzy_score(query, &key, &name) {
+                res.push((score, key, name));
+            }
+        }
+        res.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        Ok(res.into_iter().map(|(_, key, name)| (key, name)).collect())
+    }
+
+    // An error for a food that doesn't exist, suggesting similar foods.
+    pub fn food_not_found(&self, key: &str) -> anyhow::Error {
+        let suggestions: Vec<_> = match self.find_food(key) {
+            Ok(found) => found
+                .into_iter()
+                .take(3)
+                .map(|(key, _)| format!("{key:?}"))
+                .collect(),
+            Err(err) => {
+                log::warn!("Failed to find foods like '{key}': {err:?}");
+                vec![]
+            }
+        };
+        match suggestions.len() {
+            0 => anyhow!("No food with key {key:?}"),
+            1 => anyhow!("No food with key {key:?}, did you mean {}?", suggestions[0]),
+            _ => anyhow!(
+                "No food with key {key:?}, did you mean one of {}?",
+                suggestions.join(", ")
+            ),
+        }
+    }
+
+    // If key is not a food, but is an alias of one, return the key of that food.
+    pub fn resolve_alias(&self, key: &str) -> Result<Option<String>> {
+        if self.dir.join(Food::path(key)).exists() || !self.dir.join(Food::DIR).exists() {
+            return Ok(None);
+        }
+        for k in self
+            .list_food()?
+            .filter_map(|k| k.map_err(|e| log::warn!("{e:?}")).ok())
+        {
+            match self.load_food_shallow(&k) {
+                Ok(food) if food.aliases.iter().any(|a| a == key) => return Ok(Some(k)),
+                Ok(_) => {}
+                Err(err) => log::warn!("Failed to load food '{k}': {err:?}"),
+            }
+        }
+        Ok(None)
+    }
+
+    // Read a food without loading its ingredients.
+    // This is enough to get the name and aliases.
+    fn load_food_shallow(&self, key: &str) -> Result<Food> {
         let path = self.dir.join(Food::path(key));
-        log::debug!("Saving {data:?} to {path:?}");
-        fs::create_dir_all(
-            path.parent()
-                .ok_or_else(|| anyhow!("No parent path: {path:?}"))?,
-        )?;
-        let file = std::fs::File::create(&path).with_context(|| format!("Open {path:?}"))?;
-        let mut writer = BufWriter::new(&file);
-        data.save(&mut writer)?;
-        Ok(())
+        let file = std::fs::File::open(&path).with_context(|| format!("Open {path:?}"))?;
+        Food::load(BufReader::new(file), |_| Ok(Some(Food::default())))
+    }
+
+    // Return each alias of a food that is already the key or an alias of another food,
+    // along with the key of that food, as load_food could only find one of them.
+    pub fn alias_clashes(&self, key: &str, food: &Food) -> Result<Vec<(String, String)>> {
+        let mut res = vec![];
+        for alias in &food.aliases {
+            let other = if self.dir.join(Food::path(alias)).exists() {
+                Some(alias.clone())
+            } else {
+                self.resolve_alias(alias)?
+            };
+            if let Some(other) = other.filter(|k| k != key) {
+                res.push((alias.clone(), other));
+            }
+        }
+        Ok(res)
     }
 
+    pub fn save_food(&self, key: &str, data: &Food) -> Result<()> {
+        log::debug!("Saving {data:?} to {key}");
+        if let Some((alias, other)) = self.alias_clashes(key, data)?.into_iter().next() {
+            bail!("Alias {alias:?} is already used by food {other:?}");
+        }
+        self.save::<Food>(key, data, || format!("save food {key}"))
+    }
+
+    // Load a food by key or alias.
     pub fn load_food(&self, key: &str) -> Result<Option<Food>> {
         let path = self.dir.join(Food::path(key));
         log::debug!("Loading {path:?}");
         let file = match std::fs::File::open(&path) {
             Ok(f) => f,
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return match self.resolve_alias(key)? {
+                    Some(k) => {
+                        log::debug!("Resolved alias {key} to {k}");
+                        self.load_food(&k)
+                    }
+                    None => Ok(None),
+                };
+            }
             Err(e) => {
                 bail!("Failed to open '{path:?}': {e}")
             }
         };
         let reader = BufReader::new(file);
-        Ok(Some(Food::load(reader, |key| self.load_food(key))?))
+        Ok(Some(Food::load(reader, |key| self.load_ingredient(key))?))
+    }
+
+    // Return the dates of all journals, in order.
+    pub fn list_journal(&self) -> Result<Vec<NaiveDate>> {
+        let dir = self.dir.join(Journal::DIR);
+        log::trace!("Listing {dir:?}");
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut res = vec![];
+        for path in walk(&dir)? {
+            let Some(date) = path
+                .strip_prefix(&dir)
+                .ok()
+                .and_then(|p| p.with_extension("").to_str().map(str::to_string))
+                .and_then(|p| NaiveDate::parse_from_str(&p, "%Y/%m/%d").ok())
+            else {
+                log::warn!("Ignoring unexpected journal file {path:?}");
+                continue;
+            };
+            res.push(date);
+        }
+        res.sort();
+        Ok(res)
+    }
+
+    // Rename a food, rewriting every recipe, journal and meal that refers to it.
+    // Returns the paths of the files that were changed, relative to the root.
+    // If dry_run is true, nothing is changed.
+    pub fn rename_food(&self, old: &str, new: &str, dry_run: bool) -> Result<Vec<PathBuf>> {
+        if !self.dir.join(Food::path(old)).exists() {
+            return Err(self.food_not_found(old));
+        }
+        if new.is_empty() || new.contains(['/', '=', '[', ']']) {
+            bail!("Invalid food key {new:?}");
+        }
+        if self.load_food(new)?.is_some() {
+            bail!("Food with key {new:?} already exists");
+        }
+
+        // Rewrite everything in memory first, so nothing is changed if a file fails to read.
+        let mut changes = vec![];
+        for key in self.list_food()? {
+            let key = key?;
+            if key == old {
+                continue;
+            }
+            let path = Food::path(&key);
+            let text = fs::read_to_string(self.dir.join(&path))
+                .with_context(|| format!("Read {path:?}"))?;
+            if let Some(text) = refs::rename_key(&text, Some("ingredients"), old, new) {
+                changes.push((path, text));
+            }
+        }
+        let journals = self.list_journal()?.into_iter().map(|d| Journal::path(&d));
+        let meals = self.list_meal()?.into_iter().map(|k| Meal::path(&k));
+        for path in journals.chain(meals) {
+            let text = fs::read_to_string(self.dir.join(&path))
+                .with_context(|| format!("Read {path:?}"))?;
+            if refs::journal_keys(&text).all(|r| r.key != old) {
+                continue;
+            }
+            if let Some(text) = refs::rename_key(&text, None, old, new) {
+                changes.push((path, text));
+            }
+        }
+
+        changes.sort();
+        let mut res = vec![Food::path(old)];
+        res.extend(changes.iter().map(|(path, _)| path.clone()));
+        if dry_run {
+            return Ok(res);
+        }
+
+        self.transaction(&format!("rename food {old} to {new}"), || {
+            for (path, text) in changes {
+                log::debug!("Rewriting references to {old} in {path:?}");
+                fs::write(self.dir.join(&path), text).with_context(|| format!("Write {path:?}"))?;
+                self.record(path, String::new)?;
+            }
+            let (from, to) = (
+                self.dir.join(Food::path(old)),
+                self.dir.join(Food::path(new)),
+            );
+            log::debug!("Moving {from:?} to {to:?}");
+            fs::rename(&from, &to).with_context(|| format!("Move {from:?} to {to:?}"))?;
+            self.record(Food::path(old), String::new)?;
+            self.record(Food::path(new), String::new)
+        })?;
+        Ok(res)
+    }
+
+    // Return the recipes, journals and meals that refer to a food by its key or aliases,
+    // as (path, line) with the path relative to the root.
+    pub fn find_references(&self, key: &str) -> Result<Vec<(PathBuf, usize)>> {
+        let mut keys = vec![key.to_string()];
+        if let Ok(food) = self.load_food_shallow(key) {
+            keys.extend(food.aliases);
+        }
+        let is_ref = |r: &refs::Ref| keys.iter().any(|k| k == r.key);
+
+        let mut res = vec![];
+        for k in self.list_food()? {
+            let k = k?;
+            if k == key {
+                continue;
+            }
+            let path = Food::path(&k);
+            let text = fs::read_to_string(self.dir.join(&path))
+                .with_context(|| format!("Read {path:?}"))?;
+            res.extend(
+                refs::recipe_keys(&text)
+                    .filter(is_ref)
+                    .map(|r| (path.clone(), r.line)),
+            );
+        }
+        let journals = self.list_journal()?.into_iter().map(|d| Journal::path(&d));
+        let meals = self.list_meal()?.into_iter().map(|k| Meal::path(&k));
+        for path in journals.chain(meals) {
+            let text = fs::read_to_string(self.dir.join(&path))
+                .with_context(|| format!("Read {path:?}"))?;
+            res.extend(
+                refs::journal_keys(&text)
+                    .filter(is_ref)
+                    .map(|r| (path.clone(), r.line)),
+            );
+        }
+        res.sort();
+        Ok(res)
+    }
+
+    // Copy a food into every journal that refers to it by its key or aliases,
+    // so those journals still load once the food is removed.
+    // Returns the dates of the journals that were changed.
+    pub fn snapshot_food(&self, key: &str) -> Result<Vec<NaiveDate>> {
+        let mut keys = vec![key.to_string()];
+        keys.extend(self.load_food_shallow(key)?.aliases);
+
+        let mut res = vec![];
+        for date in self.list_journal()? {
+            let Some(mut journal) = self.load_journal(&date)? else {
+                continue;
+            };
+            let mut changed = false;
+            for k in &keys {
+                changed |= journal.inline(k);
+            }
+            if changed {
+                log::debug!("Copied {key} into journal {date}");
+                self.save_journal(&date, &journal)?;
+                res.push(date);
+            }
+        }
+        Ok(res)
+    }
+
+    // Look for problems in every food, journal, meal and the goals, such as files that don't parse,
+    // or refer to foods that don't exist.
+    pub fn check(&self) -> Result<Vec<check::Problem>> {
+        let mut res = vec![];
+        self.check_dir::<Food>(false, &mut res)?;
+        self.check_dir::<Journal>(true, &mut res)?;
+        self.check_dir::<Meal>(false, &mut res)?;
+        self.check_dir::<Body>(true, &mut res)?;
+        self.check_dir::<Activity>(false, &mut res)?;
+        self.check_dir::<Exercise>(true, &mut res)?;
+        let goals = Goals::path(&());
+        if let Some(text) = self.load_text::<Goals>(&())? {
+            res.extend(Goals::check(self, &goals, &text));
+        }
+        res.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        Ok(res)
+    }
+
+    // Check every file of a type, whose keys are either dates or names.
+    fn check_dir<T: Data>(&self, dated: bool, res: &mut Vec<check::Problem>) -> Result<()> {
+        let root = self.dir.join(T::DIR);
+        if !root.exists() {
+            return Ok(());
+        }
+        for path in walk(&root)? {
+            let rel = path.strip_prefix(&self.dir)?.to_path_buf();
+            log::debug!("Checking {rel:?}");
+            let problem = |message: &str| check::Problem {
+                path: rel.clone(),
+                line: None,
+                message: message.into(),
+            };
+
+            let name = path.strip_prefix(&root)?.with_extension("");
+            let Some(name) = name.to_str() else {
+                res.push(problem("File name is not UTF-8"));
+                continue;
+            };
+            if path.extension().is_none_or(|ext| ext != "txt") {
+                res.push(problem("Expected a .txt file"));
+                continue;
+            }
+            let (valid, layout) = if dated {
+                (
+                    NaiveDate::parse_from_str(name, "%Y/%m/%d").is_ok(),
+                    "<year>/<month>/<day>",
+                )
+            } else {
+                (!name.contains(std::path::MAIN_SEPARATOR), "<key>")
+            };
+            if !valid {
+                res.push(problem(&format!("Expected {}/{layout}.txt", T::DIR)));
+                continue;
+            }
+
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(err) => {
+                    res.push(problem(&format!("Failed to read: {err}")));
+                    continue;
+                }
+            };
+            res.extend(T::check(self, &rel, &text));
+        }
+        Ok(())
     }
 
     pub fn save_journal(&self, key: &NaiveDate, data: &Journal) -> Result<()> {
-        let path = self.dir.join(Journal::path(key));
-        log::debug!("Saving {data:?} to {path:?}");
+        log::debug!("Saving {data:?} to {key}");
+        self.save::<Journal>(key, data, || format!("save journal {key}"))
+    }
+
+    pub fn load_journal(&self, key: &NaiveDate) -> Result<Option<Journal>> {
+        self.load::<Journal>(key)
+    }
+
+    // Load a food referenced by another file, such as a recipe or journal.
+    pub fn load_ingredient(&self, key: &str) -> Result<Option<Food>> {
+        match self.load_food(key)? {
+            Some(food) => Ok(Some(food)),
+            None => Err(self.food_not_found(key)),
+        }
+    }
+
+    // Return the keys of all meals, in order.
+    pub fn list_meal(&self) -> Result<Vec<String>> {
+        let dir = self.dir.join(Meal::DIR);
+        log::trace!("Listing {dir:?}");
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut res = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path().with_extension("");
+            match path.file_name().and_then(|f| f.to_str()) {
+                Some(key) => res.push(key.to_string()),
+                None => log::warn!("Ignoring unexpected meal file {path:?}"),
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+
+    pub fn save_meal(&self, key: &str, data: &Meal) -> Result<()> {
+        log::debug!("Saving {data:?} to {key}");
+        self.save::<Meal>(key, data, || format!("save meal {key}"))
+    }
+
+    pub fn load_meal(&self, key: &str) -> Result<Option<Meal>> {
+        self.load::<Meal>(key)
+    }
+
+    pub fn save_body(&self, key: &NaiveDate, data: &Body) -> Result<()> {
+        log::debug!("Saving {data:?} to {key}");
+        self.save::<Body>(key, data, || format!("save body {key}"))
+    }
+
+    pub fn load_body(&self, key: &NaiveDate) -> Result<Option<Body>> {
+        self.load::<Body>(key)
+    }
+
+    // Return the keys of all activities, in order.
+    pub fn list_activity(&self) -> Result<Vec<String>> {
+        let dir = self.dir.join(Activity::DIR);
+        log::trace!("Listing {dir:?}");
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut res = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path().with_extension("");
+            match path.file_name().and_then(|f| f.to_str()) {
+                Some(key) => res.push(key.to_string()),
+                None => log::warn!("Ignoring unexpected activity file {path:?}"),
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+
+    pub fn save_activity(&self, key: &str, data: &Activity) -> Result<()> {
+        log::debug!("Saving {data:?} to {key}");
+        self.save::<Activity>(key, data, || format!("save activity {key}"))
+    }
+
+    pub fn load_activity(&self, key: &str) -> Result<Option<Activity>> {
+        self.load::<Activity>(key)
+    }
+
+    pub fn save_exercise(&self, key: &NaiveDate, data: &Exercise) -> Result<()> {
+        log::debug!("Saving {data:?} to {key}");
+        self.save::<Exercise>(key, data, || format!("save exercise {key}"))
+    }
+
+    pub fn load_exercise(&self, key: &NaiveDate) -> Result<Option<Exercise>> {
+        self.load::<Exercise>(key)
+    }
+
+    pub fn save_goals(&self, data: &Goals) -> Result<()> {
+        log::debug!("Saving {data:?}");
+        self.save::<Goals>(&(), data, || "save goals".into())
+    }
+
+    // Load the daily goals, which are the defaults if none have been set.
+    pub fn load_goals(&self) -> Result<Goals> {
+        Ok(self.load::<Goals>(&())?.unwrap_or_default())
+    }
+
+    // Return the most recent weight on or before a day, looking back up to a month.
+    pub fn weight_on(&self, date: NaiveDate) -> Result<Option<Weight>> {
+        for days in 0..31 {
+            let day = date - chrono::Days::new(days);
+            if let Some(weight) = self.load_body(&day)?.and_then(|b| b.weight) {
+                return Ok(Some(weight));
+            }
+        }
+        Ok(None)
+    }
+
+    // Return the kcal burned by an entry of exercise done on a day.
+    // This is the kcal recorded with the entry, if any, or else is worked out
+    // from the activity and the weight on that day.
+    pub fn burned(&self, date: NaiveDate, entry: &ExerciseEntry) -> Result<f32> {
+        let minutes = match (entry.effort, entry.kcal) {
+            (Effort::Kcal(kcal), _) | (_, Some(kcal)) => return Ok(kcal),
+            (Effort::Minutes(minutes), None) => minutes,
+        };
+        let activity = self
+            .load_activity(&entry.key)?
+            .with_context(|| format!("No activity with key {:?}", entry.key))?;
+        activity.kcal(minutes, self.weight_on(date)?.as_ref())
+    }
+
+    // Return the kcal eaten on each day from start to end, inclusive, that has a journal.
+    pub fn intake(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<(NaiveDate, f32)>> {
+        let mut res = vec![];
+        for day in start.iter_days().take_while(|d| *d <= end) {
+            if let Some(journal) = self.load_journal(&day)? {
+                res.push((day, journal.nutrients()?.kcal));
+            }
+        }
+        Ok(res)
+    }
+
+    // Return the weight on each day from start to end, inclusive, that has one.
+    pub fn weights(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        unit: WeightUnit,
+    ) -> Result<Vec<(NaiveDate, f32)>> {
+        let mut res = vec![];
+        for day in start.iter_days().take_while(|d| *d <= end) {
+            if let Some(weight) = self.load_body(&day)?.and_then(|b| b.weight) {
+                res.push((day, weight.to(unit)));
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn save_index(&self, index: &Index) -> Result<()> {
+        let path = self.dir.join(Self::INDEX_PATH);
+        log::debug!("Saving {} foods to {path:?}", index.len());
         fs::create_dir_all(
             path.parent()
                 .ok_or_else(|| anyhow!("No parent path: {path:?}"))?,
         )?;
         let file = std::fs::File::create(&path).with_context(|| format!("Open {path:?}"))?;
         let mut writer = BufWriter::new(&file);
-        data.save(&mut writer)?;
-        Ok(())
+        index.save(&mut writer)
     }
 
-    pub fn load_journal(&self, key: &NaiveDate) -> Result<Option<Journal>> {
-        let path = self.dir.join(Journal::path(key));
+    pub fn load_index(&self) -> Result<Option<Index>> {
+        let path = self.dir.join(Self::INDEX_PATH);
         log::debug!("Loading {path:?}");
         let file = match std::fs::File::open(&path) {
             Ok(f) => f,
@@ -113,13 +759,28 @@ impl Database {
                 bail!("Failed to open '{path:?}': {e}")
             }
         };
-        let reader = BufReader::new(file);
-        Ok(Some(Journal::load(reader, |key| self.load_food(key))?))
+        Ok(Some(Index::load(BufReader::new(file))?))
     }
 
     pub fn remove<T: Data>(&self, key: &T::Key) -> Result<()> {
-        Ok(std::fs::remove_file(&self.dir.join(T::path(key)))?)
+        let path = T::path(key);
+        std::fs::remove_file(self.dir.join(&path))?;
+        self.record(path.clone(), || format!("remove {}", path.display()))
+    }
+}
+
+// Return the paths of all files under a directory.
+fn walk(dir: &std::path::Path) -> Result<Vec<PathBuf>> {
+    let mut res = vec![];
+    for entry in fs::read_dir(dir).with_context(|| format!("Read {dir:?}"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            res.extend(walk(&path)?);
+        } else {
+            res.push(path);
+        }
     }
+    Ok(res)
 }
 
 #[cfg(test)]
@@ -144,7 +805,7 @@ mod tests {
         }
     }
 
-    fn setup() -> (Database, tempfile::TempDir) {
+    pub(crate) fn setup() -> (Database, tempfile::TempDir) {
         let _ = env_logger::try_init();
         let tmp = tempfile::tempdir().unwrap();
         let data = Database::new(tmp.path()).unwrap();
@@ -167,10 +828,46 @@ mod tests {
                     kcal: 382.0,
                 }),
                 servings: vec![("cups".into(), 0.5), ("g".into(), 100.0)],
+                aliases: vec!["porridge".into(), "oatmeal".into()],
+                ..Default::default()
             }
         );
     }
 
+    #[test]
+    fn test_load_food_alias() {
+        let (data, _tmp) = setup();
+        let oats = data.load_food("oats").unwrap().unwrap();
+        assert_eq!(data.load_food("porridge").unwrap().unwrap(), oats);
+        assert_eq!(data.load_food("oatmeal").unwrap().unwrap(), oats);
+        assert_eq!(
+            data.resolve_alias("porridge").unwrap(),
+            Some("oats".to_string())
+        );
+        assert_eq!(data.resolve_alias("oats").unwrap(), None);
+        assert_eq!(data.resolve_alias("nope").unwrap(), None);
+    }
+
+    #[test]
+    fn test_save_food_alias_clash() {
+        let (data, _tmp) = setup();
+        let mut banana = data.load_food("banana").unwrap().unwrap();
+        // Aliases may not be another food's key or alias.
+        for (alias, other) in [("oats", "oats"), ("porridge", "oats")] {
+            banana.aliases = vec!["plantain".into(), alias.into()];
+            assert_eq!(
+                data.save_food("banana", &banana).unwrap_err().to_string(),
+                format!("Alias {alias:?} is already used by food {other:?}")
+            );
+        }
+        // A food may keep its own aliases.
+        let oats = data.load_food("oats").unwrap().unwrap();
+        data.save_food("oats", &oats).unwrap();
+        banana.aliases = vec!["plantain".into()];
+        data.save_food("banana", &banana).unwrap();
+        assert_eq!(data.load_food("plantain").unwrap().unwrap(), banana);
+    }
+
     #[test]
     fn test_load_food_recipe() {
         let (data, _tmp) = setup();
@@ -195,6 +892,8 @@ mod tests {
                                 kcal: 382.0,
                             }),
                             servings: vec![("cups".into(), 0.5), ("g".into(), 100.0)],
+                            aliases: vec!["porridge".into(), "oatmeal".into()],
+                            ..Default::default()
                         },
                     },
                     Ingredient {
@@ -212,10 +911,12 @@ mod tests {
                                 kcal: 98.0
                             }),
                             servings: vec![("g".into(), 100.0)],
+                            ..Default::default()
                         },
                     },
                 ]),
                 servings: vec![("cups".into(), 0.5), ("g".into(), 100.0)],
+                ..Default::default()
             }
         );
     }
@@ -246,6 +947,324 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_find_food() {
+        let (data, _tmp) = setup();
+        let find = |query| {
+            data.find_food(query)
+                .unwrap()
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(find("oat"), vec!["oats", "banana_oatmeal"]);
+        assert_eq!(find("bnoat"), vec!["banana_oatmeal"]);
+        // Matches names as well as keys.
+        assert_eq!(find("Banana Oat"), vec!["banana_oatmeal"]);
+        // Typos that aren't subsequences are matched by edit distance.
+        assert_eq!(find("oast"), vec!["oats"]);
+        assert!(find("nope").is_empty());
+    }
+
+    #[test]
+    fn test_food_not_found() {
+        let (data, _tmp) = setup();
+        assert_eq!(
+            data.food_not_found("nope").to_string(),
+            r#"No food with key "nope""#
+        );
+        assert_eq!(
+            data.food_not_found("oast").to_string(),
+            r#"No food with key "oast", did you mean "oats"?"#
+        );
+        assert_eq!(
+            data.food_not_found("bana").to_string(),
+            r#"No food with key "bana", did you mean one of "banana", "banana_oatmeal"?"#
+        );
+    }
+
+    #[test]
+    fn test_load_food_missing_ingredient() {
+        let (data, tmp) = setup();
+        fs::write(
+            tmp.path().join("food/granola.txt"),
+            "name = Granola\n[ingredients]\noast = 1\n",
+        )
+        .unwrap();
+        let err = data.load_food("granola").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"No food with key "oast", did you mean "oats"?"#
+        );
+    }
+
+    #[test]
+    fn test_list_journal() {
+        let (data, _tmp) = setup();
+        assert_eq!(
+            data.list_journal().unwrap(),
+            vec![
+                NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_food() {
+        let (data, tmp) = setup();
+        let expected = vec![
+            PathBuf::from("food/oats.txt"),
+            PathBuf::from("food/banana_oatmeal.txt"),
+            PathBuf::from("journal/2024/06/30.txt"),
+            PathBuf::from("journal/2024/07/01.txt"),
+            PathBuf::from("meal/breakfast.txt"),
+        ];
+
+        assert_eq!(
+            data.rename_food("oats", "rolled_oats", true).unwrap(),
+            expected
+        );
+        assert!(data.load_food("oats").unwrap().is_some());
+
+        assert_eq!(
+            data.rename_food("oats", "rolled_oats", false).unwrap(),
+            expected
+        );
+        assert!(data.load_food("oats").unwrap().is_none());
+        assert_eq!(data.load_food("rolled_oats").unwrap().unwrap().name, "Oats");
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("journal/2024/07/01.txt")).unwrap(),
+            "banana = 1\nrolled_oats = 0.5c\nrolled_oats = 1.0\nbanana = 50g\n"
+        );
+        let recipe = data.load_food("banana_oatmeal").unwrap().unwrap();
+        let FoodSpec::Ingredients(ingredients) = recipe.spec else {
+            panic!("Expected ingredients");
+        };
+        assert_eq!(ingredients[0].key, "rolled_oats");
+        let meal = data.load_meal("breakfast").unwrap().unwrap();
+        assert_eq!(meal.0[0].key, "rolled_oats");
+    }
+
+    #[test]
+    fn test_rename_food_exists() {
+        let (data, _tmp) = setup();
+        assert!(data.rename_food("oats", "banana", false).is_err());
+        assert!(data.rename_food("nope", "other", false).is_err());
+        assert!(data.rename_food("oats", "a/b", false).is_err());
+    }
+
+    #[test]
+    fn test_meal() {
+        let (data, _tmp) = setup();
+        assert_eq!(data.list_meal().unwrap(), vec!["breakfast"]);
+        let meal = data.load_meal("breakfast").unwrap().unwrap();
+        assert_eq!(
+            meal.0
+                .iter()
+                .map(|e| (e.key.as_str(), e.serving.to_string()))
+                .collect::<Vec<_>>(),
+            vec![("oats", "0.5 c".into()), ("banana", "1".into())]
+        );
+
+        // Add the meal to a journal with a different inline banana, which it keeps apart.
+        let date = NaiveDate::from_ymd_opt(2024, 7, 2).unwrap();
+        let mut journal = Journal::default();
+        let mut banana = data.load_food("banana").unwrap().unwrap();
+        banana.name = "Plantain".into();
+        journal.0.push(JournalEntry {
+            key: "banana".into(),
+            serving: Serving::default(),
+            food: banana,
+            inline: true,
+            ..Default::default()
+        });
+        journal.extend(meal.0);
+        data.save_journal(&date, &journal).unwrap();
+
+        let journal = data.load_journal(&date).unwrap().unwrap();
+        assert_eq!(
+            journal
+                .0
+                .iter()
+                .map(|e| (e.key.as_str(), e.food.name.as_str(), e.inline))
+                .collect::<Vec<_>>(),
+            vec![
+                ("banana", "Plantain", true),
+                ("oats", "Oats", false),
+                ("banana_2", "Banana", true),
+            ]
+        );
+        assert!(data.load_meal("lunch").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_git_history() {
+        let (_, tmp) = setup();
+        let git = Git::init(tmp.path()).unwrap();
+        git.run(&["config", "user.name", "nosh"]).unwrap();
+        git.run(&["config", "user.email", "nosh@example.com"])
+            .unwrap();
+        git.commit(&[".".into()], "init").unwrap();
+        let data = Database::new(tmp.path()).unwrap();
+        let messages = || -> Vec<_> {
+            let log = data.git().unwrap().log(10).unwrap();
+            log.into_iter().map(|c| c.message).collect()
+        };
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let mut journal = data.load_journal(&date).unwrap().unwrap();
+        journal.0.pop();
+        data.save_journal(&date, &journal).unwrap();
+        data.rename_food("oats", "rolled_oats", false).unwrap();
+        assert_eq!(
+            messages(),
+            vec![
+                "rename food oats to rolled_oats",
+                "save journal 2024-07-01",
+                "init"
+            ]
+        );
+
+        let undone = data.git().unwrap().undo().unwrap().unwrap();
+        assert_eq!(undone.message, "rename food oats to rolled_oats");
+        assert!(data.load_food("oats").unwrap().is_some());
+        assert!(data.load_food("rolled_oats").unwrap().is_none());
+        let undone = data.git().unwrap().undo().unwrap().unwrap();
+        assert_eq!(undone.message, "save journal 2024-07-01");
+        assert_eq!(data.load_journal(&date).unwrap().unwrap().0.len(), 4);
+    }
+
+    #[test]
+    fn test_transaction_commit() {
+        let (_, tmp) = setup();
+        let git = Git::init(tmp.path()).unwrap();
+        git.run(&["config", "user.name", "nosh"]).unwrap();
+        git.run(&["config", "user.email", "nosh@example.com"])
+            .unwrap();
+        git.commit(&[".".into()], "init").unwrap();
+        let data = Database::new(tmp.path()).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let mut journal = data.load_journal(&date).unwrap().unwrap();
+
+        // Files written before a failure are still committed.
+        journal.0.pop();
+        let res: Result<()> = data.transaction("partial", || {
+            data.save_journal(&date, &journal)?;
+            bail!("failed")
+        });
+        assert!(res.is_err());
+        let log = data.git().unwrap().log(1).unwrap();
+        assert_eq!(log[0].message, "partial");
+
+        // If committing fails, the change is still saved.
+        fs::write(tmp.path().join(".git/index.lock"), "").unwrap();
+        journal.0.pop();
+        data.transaction("locked", || data.save_journal(&date, &journal))
+            .unwrap();
+        assert_eq!(data.load_journal(&date).unwrap().unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn test_find_references() {
+        let (data, _tmp) = setup();
+        assert_eq!(
+            data.find_references("oats").unwrap(),
+            vec![
+                (PathBuf::from("food/banana_oatmeal.txt"), 4),
+                (PathBuf::from("journal/2024/06/30.txt"), 2),
+                (PathBuf::from("journal/2024/07/01.txt"), 2),
+                (PathBuf::from("journal/2024/07/01.txt"), 3),
+                (PathBuf::from("meal/breakfast.txt"), 1),
+            ]
+        );
+        assert!(data.find_references("banana_oatmeal").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_extend_inline_clash() {
+        let (data, _tmp) = setup();
+        let from = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        // Snapshot oats on one day, then change the food.
+        let mut old = data.load_journal(&from).unwrap().unwrap();
+        old.inline("oats");
+        let mut oats = data.load_food("oats").unwrap().unwrap();
+        oats.spec = FoodSpec::Nutrients(Nutrients {
+            kcal: 400.0,
+            ..Default::default()
+        });
+        data.save_food("oats", &oats).unwrap();
+
+        // Copying the snapshot to a day that uses the new food keeps both.
+        let mut journal = data.load_journal(&to).unwrap().unwrap();
+        journal.extend(old.0);
+        let expected = journal.nutrients().unwrap();
+        data.save_journal(&to, &journal).unwrap();
+        let loaded = data.load_journal(&to).unwrap().unwrap();
+        assert_eq!(loaded.nutrients().unwrap(), expected);
+        assert_eq!(
+            loaded.0.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(),
+            vec!["banana", "oats", "oats", "banana", "banana", "oats_2"]
+        );
+        // The entries of the new food still refer to it, and the snapshot is inline.
+        assert_eq!(loaded.0[1].food.nutrients().kcal, 400.0);
+        assert!(!loaded.0[1].inline);
+        assert!(loaded.0[5].inline);
+        assert_eq!(loaded.0[5].food.nutrients().kcal, 382.0);
+    }
+
+    #[test]
+    fn test_snapshot_food() {
+        let (data, tmp) = setup();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let before = data
+            .load_journal(&date)
+            .unwrap()
+            .unwrap()
+            .nutrients()
+            .unwrap();
+
+        assert_eq!(
+            data.snapshot_food("oats").unwrap(),
+            vec![date, NaiveDate::from_ymd_opt(2024, 7, 1).unwrap()]
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("journal/2024/06/30.txt")).unwrap(),
+            [
+                "banana = 1.5",
+                "oats = 150g",
+                "",
+                "[oats]",
+                "name = Oats",
+                "carb = 68.7",
+                "fat = 5.89",
+                "protein = 13.5",
+                "kcal = 382",
+                "servings = 0.5 cups, 100 g",
+                "",
+            ]
+            .join("\n")
+        );
+
+        // The journals no longer depend on the food.
+        assert_eq!(
+            data.find_references("oats").unwrap(),
+            vec![
+                (PathBuf::from("food/banana_oatmeal.txt"), 4),
+                (PathBuf::from("meal/breakfast.txt"), 1),
+            ]
+        );
+        data.remove::<Food>("oats").unwrap();
+        let after = data
+            .load_journal(&date)
+            .unwrap()
+            .unwrap()
+            .nutrients()
+            .unwrap();
+        assert_eq!(before, after);
+    }
+
     #[test]
     fn test_save_food() {
         let (data, tmp) = setup();
@@ -258,6 +1277,8 @@ mod tests {
                 kcal: 120.0,
             }),
             servings: vec![("g".into(), 50.0), ("cups".into(), 2.5)],
+            aliases: vec!["flakes".into(), "cornflakes".into()],
+            ..Default::default()
         };
         data.save_food("cereal", &food).unwrap();
         let res = fs::read_to_string(tmp.path().join("food/cereal.txt")).unwrap();
@@ -265,6 +1286,7 @@ mod tests {
             res,
             [
                 "name = Cereal",
+                "aliases = flakes, cornflakes",
                 "",
                 "[nutrients]",
                 "carb = 22",
@@ -285,7 +1307,7 @@ mod tests {
     fn test_load_journal_not_exists() {
         let tmp = tempfile::tempdir().unwrap();
         let data = Database::new(tmp.path()).unwrap();
-        let date = &chrono::NaiveDate::from_ymd_opt(2024, 07, 01).unwrap();
+        let date = &chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
         let actual = data.load_journal(&date.clone()).unwrap();
         assert!(actual.is_none());
     }
@@ -298,6 +1320,7 @@ mod tests {
             key: key.into(),
             serving: Serving { size, unit },
             food: data.load_food(key).unwrap().unwrap(),
+            ..Default::default()
         };
         let expected = Journal(vec![
             serv("banana", 1.0, None),
@@ -306,7 +1329,7 @@ mod tests {
             serv("banana", 50.0, Some("g".into())),
         ]);
 
-        let date = &chrono::NaiveDate::from_ymd_opt(2024, 07, 01).unwrap();
+        let date = &chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
         let actual: Journal = data.load_journal(&date.clone()).unwrap().unwrap();
         assert_eq!(expected, actual);
     }
@@ -326,7 +1349,7 @@ mod tests {
             serv("cereal", 50.0, Some("g".into())),
         ]);
 
-        let date = &chrono::NaiveDate::from_ymd_opt(2024, 07, 08).unwrap();
+        let date = &chrono::NaiveDate::from_ymd_opt(2024, 7, 8).unwrap();
         data.save_journal(&date.clone(), &expected).unwrap();
 
         let actual = fs::read_to_string(
@@ -344,6 +1367,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_journal_time_note() {
+        let (data, tmp) = setup();
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 7, 8).unwrap();
+        let entry = |time: Option<&str>, note: Option<&str>| JournalEntry {
+            key: "oats".into(),
+            food: data.load_food("oats").unwrap().unwrap(),
+            time: time.map(|t| journal::parse_time(t).unwrap()),
+            note: note.map(str::to_string),
+            ..Default::default()
+        };
+        let expected = Journal(vec![
+            entry(None, None),
+            entry(Some("08:15"), None),
+            entry(Some("12:30"), Some("weighed | after cooking")),
+            entry(None, Some("leftovers")),
+        ]);
+        data.save_journal(&date, &expected).unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("journal/2024/07/08.txt")).unwrap(),
+            [
+                "oats = 1",
+                "oats = 1 | 08:15",
+                "oats = 1 | 12:30 | weighed | after cooking",
+                "oats = 1 | | leftovers",
+                ""
+            ]
+            .join("\n")
+        );
+        assert_eq!(data.load_journal(&date).unwrap().unwrap(), expected);
+    }
+
     // #[test]
     // fn test_save_recipe() {
     //     let (data, tmp) = setup();
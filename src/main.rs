@@ -1,7 +1,13 @@
-use anyhow::{anyhow, bail, Context, Result};
-use clap::{Parser, Subcommand};
-use nosh::{Database, Food, JournalEntry, Nutrients, Serving, APP_NAME};
-use std::{fs, io::Write};
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use nosh::{
+    Activity, Database, ExerciseEntry, Food, JournalEntry, Meal, Nutrients, Serving, Weight,
+    WeightUnit, APP_NAME, TIME_FORMAT,
+};
+use std::{
+    io::{IsTerminal, Write},
+    path::PathBuf,
+};
 use tabled::{
     settings::{
         object::Rows,
@@ -15,24 +21,292 @@ use terminal_size::{terminal_size, Height};
 
 #[derive(Subcommand)]
 enum FoodCommand {
-    Edit { key: String },
-    Show { key: String },
-    Ls { term: Option<String> },
-    Rm { key: String },
-    Search { key: String, term: Option<String> },
+    // Edit a food in your editor, creating it if it doesn't exist.
+    Edit {
+        key: String,
+        // Start a new food as a recipe, with [ingredients] instead of [nutrients].
+        #[arg(long)]
+        recipe: bool,
+        // Start a new food from a search result, see `food search`.
+        #[arg(long, conflicts_with = "recipe")]
+        from_search: bool,
+    },
+    Show {
+        key: String,
+        // Also show each nutrient as a percent of its daily value (%DV), see `nosh goal`.
+        #[arg(long)]
+        dv: bool,
+    },
+    Ls {
+        term: Option<String>,
+    },
+    // Remove a food. Refuses if any recipes or journals use it, unless forced.
+    Rm {
+        key: String,
+        // Remove the food even if it is used.
+        #[arg(long)]
+        force: bool,
+        // First copy the food into the journals that use it.
+        #[arg(long)]
+        snapshot: bool,
+    },
+    // Rename a food, updating every journal and recipe that refers to it.
+    Mv {
+        old: String,
+        new: String,
+        // List the files that would change, without changing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    Search(SearchArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Provider {
+    // Food Data Central, https://fdc.nal.usda.gov.
+    Fdc,
+    // Open Food Facts, https://world.openfoodfacts.org.
+    Off,
+}
+
+#[derive(clap::Args)]
+struct SearchArgs {
+    key: String,
+    term: Option<String>,
+    // Where to search for foods.
+    #[arg(long, value_enum, default_value_t = Provider::Fdc)]
+    provider: Provider,
+    // Search the index built by `fdc import` instead of the FDC API.
+    #[arg(long, conflicts_with = "provider")]
+    offline: bool,
+    // Seconds to wait for a response before retrying.
+    #[arg(long, default_value_t = SearchArgs::TIMEOUT)]
+    timeout: u64,
+    // How many times to retry a failed request.
+    #[arg(long, default_value_t = SearchArgs::RETRIES)]
+    retries: u32,
+    // Print the first page of results as JSON and exit.
+    #[arg(long, conflicts_with_all = ["pick", "first"])]
+    json: bool,
+    // Add the result at this index on the first page, without prompting.
+    #[arg(long, conflicts_with = "first")]
+    pick: Option<usize>,
+    // Add the first result, without prompting.
+    #[arg(long)]
+    first: bool,
+    // Edit the chosen food in your editor before adding it.
+    #[arg(long, conflicts_with = "json")]
+    edit: bool,
+}
+
+impl SearchArgs {
+    const TIMEOUT: u64 = 30;
+    const RETRIES: u32 = 3;
+
+    // Search for a food to edit before adding it, as `food search --edit <key>` does.
+    fn edit(key: String) -> SearchArgs {
+        SearchArgs {
+            key,
+            term: None,
+            provider: Provider::Fdc,
+            offline: false,
+            timeout: Self::TIMEOUT,
+            retries: Self::RETRIES,
+            json: false,
+            pick: None,
+            first: false,
+            edit: true,
+        }
+    }
+}
+
+// A search result, adapted for JSON output.
+#[derive(serde::Serialize)]
+struct FoodJson<'a> {
+    index: usize,
+    name: &'a str,
+    nutrients: Nutrients,
+    servings: Vec<ServingJson<'a>>,
+    barcode: Option<&'a str>,
+    brand: Option<&'a str>,
+    ingredients: Option<&'a str>,
+}
+
+#[derive(serde::Serialize)]
+struct ServingJson<'a> {
+    unit: &'a str,
+    size: f32,
+}
+
+impl<'a> FoodJson<'a> {
+    fn new(index: usize, hit: &'a nosh::Hit) -> Self {
+        let food = &hit.food;
+        Self {
+            index,
+            name: &food.name,
+            nutrients: food.nutrients(),
+            servings: food
+                .servings
+                .iter()
+                .map(|(unit, size)| ServingJson { unit, size: *size })
+                .collect(),
+            barcode: food.barcode.as_deref(),
+            brand: hit.details.brand.as_deref(),
+            ingredients: hit.details.ingredients.as_deref(),
+        }
+    }
 }
 
 #[derive(Subcommand)]
 enum JournalCommand {
-    Edit { key: Option<String> },
-    Show { key: Option<String> },
+    Edit {
+        key: Option<String>,
+    },
+    Show {
+        key: Option<String>,
+        // Also show each nutrient as a percent of its daily value (%DV), see `nosh goal`.
+        #[arg(long)]
+        dv: bool,
+    },
+    // Remove an entry by its index, as shown by `journal show`,
+    // or every entry of a food by its key.
+    Rm {
+        entry: String,
+        // The journal to change (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+    // Change the serving of the entry at an index, as shown by `journal show`.
+    Set {
+        index: usize,
+        serving: String,
+        // The journal to change (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+    // Add the entries of one journal to another, defaulting to today.
+    Copy {
+        from: String,
+        to: Option<String>,
+        // Only copy the entries at these indexes, e.g. 0,2.
+        #[arg(short, long, value_delimiter = ',')]
+        entries: Vec<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MealCommand {
+    // Edit a meal in your editor, creating it if it doesn't exist.
+    Edit {
+        key: String,
+    },
+    Show {
+        key: String,
+    },
+    Ls,
+    Rm {
+        key: String,
+    },
+    // Save the entries of a journal as a meal.
+    Save {
+        key: String,
+        // The journal to save (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+        // Only save the entries at these indexes, e.g. 0,2.
+        #[arg(short, long, value_delimiter = ',')]
+        entries: Vec<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ActivityCommand {
+    // Edit an activity in your editor, creating it if it doesn't exist.
+    Edit { key: String },
+    Ls,
+}
+
+#[derive(Subcommand)]
+enum FdcCommand {
+    // Add foods from a Food Data Central download to the offline search index.
+    // The path may be a JSON file or a directory of extracted CSV files.
+    Import { path: PathBuf },
+}
+
+#[derive(clap::Args)]
+#[group(multiple = true, conflicts_with_all = ["repeat", "meal"])]
+struct QuickArgs {
+    #[arg(long)]
+    carb: Option<f32>,
+    #[arg(long)]
+    fat: Option<f32>,
+    #[arg(long)]
+    protein: Option<f32>,
+    #[arg(long)]
+    kcal: Option<f32>,
+}
+
+impl QuickArgs {
+    // The nutrients given, if any.
+    fn nutrients(&self) -> Option<Nutrients> {
+        let QuickArgs {
+            carb,
+            fat,
+            protein,
+            kcal,
+        } = *self;
+        if [carb, fat, protein, kcal].iter().all(Option::is_none) {
+            return None;
+        }
+        Some(
+            Nutrients {
+                carb: carb.unwrap_or_default(),
+                fat: fat.unwrap_or_default(),
+                protein: protein.unwrap_or_default(),
+                kcal: kcal.unwrap_or_default(),
+            }
+            .maybe_compute_kcal(),
+        )
+    }
 }
 
 #[derive(Subcommand)]
 enum Command {
+    // Add foods to today's journal.
+    // Each food may be followed by a serving, as in `oats 0.5c banana`,
+    // or joined to it, as in `oats=0.5c banana`.
     Eat {
-        food: String,
-        serving: Option<String>,
+        #[arg(
+            value_name = "FOOD [SERVING]",
+            required_unless_present_any = ["repeat", "meal"]
+        )]
+        foods: Vec<String>,
+        // Eat everything from another day (YYYY-MM-DD), such as yesterday.
+        #[arg(long, conflicts_with_all = ["foods", "meal"])]
+        repeat: Option<String>,
+        // Eat every food in a meal, see `nosh meal`.
+        #[arg(long, conflicts_with = "foods")]
+        meal: Option<String>,
+        // Quick-add a food that isn't in the database, such as a restaurant meal.
+        // The food is named by FOOD, and its nutrients are saved in the journal.
+        // If kcal isn't given, it is computed from the other nutrients.
+        #[command(flatten)]
+        quick: QuickArgs,
+        // A note about the food, such as "weighed after cooking", shown in `journal show`.
+        #[arg(long, conflicts_with_all = ["repeat", "meal"])]
+        note: Option<String>,
+    },
+    // Add a drink to today's journal, counting towards the daily fluid goal.
+    // Drinks water unless a food is given, as in `drink 500ml` or `drink milk 250ml`.
+    Drink {
+        #[arg(value_name = "[FOOD] VOLUME", num_args = 1..=2, required = true)]
+        args: Vec<String>,
+    },
+    // Remove the last food eaten.
+    Uneat {
+        // The journal to change (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
     },
     Food {
         #[command(subcommand)]
@@ -42,6 +316,97 @@ enum Command {
         #[command(subcommand)]
         command: JournalCommand,
     },
+    // Record exercise, as an activity and how long it was done, such as `burn running 45min`,
+    // or the kcal it burned, such as `burn 320kcal`.
+    Burn {
+        #[arg(value_name = "[ACTIVITY] AMOUNT", num_args = 1..=2, required = true)]
+        args: Vec<String>,
+        // The day of the exercise (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+    // Manage activities, which are kinds of exercise that burn energy at a rate.
+    Activity {
+        #[command(subcommand)]
+        command: ActivityCommand,
+    },
+    // Manage meals, which are lists of foods to eat at once.
+    Meal {
+        #[command(subcommand)]
+        command: MealCommand,
+    },
+    Fdc {
+        #[command(subcommand)]
+        command: FdcCommand,
+    },
+    // Check all foods and journals for problems.
+    // Exits with an error if any are found.
+    Check,
+    // Show daily goals, or set one, such as `goal fluid 2.5l`.
+    Goal {
+        #[arg(requires = "value")]
+        name: Option<String>,
+        value: Option<String>,
+    },
+    // Record your weight, such as 81.3kg or 179lb.
+    Weigh {
+        weight: String,
+        // The day weighed (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+        // The unit of a weight given without one.
+        #[arg(long, default_value = "kg")]
+        unit: WeightUnit,
+    },
+    // Record a body measurement, such as waist 86cm.
+    Measure {
+        name: String,
+        value: String,
+        // The day measured (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+    // Show the nutrients eaten each day, next to weight and its trend.
+    // The trend is the average weight over the last week.
+    Report {
+        // The number of days to show.
+        #[arg(short = 'n', long, default_value_t = 14)]
+        days: u64,
+        // The last day to show (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+        // The unit to show weights in.
+        #[arg(long, default_value = "kg")]
+        unit: WeightUnit,
+    },
+    // Estimate the kcal you burn per day (TDEE) from what you ate and your weight trend,
+    // and suggest kcal goals to lose, keep or gain weight.
+    Tdee {
+        // The number of days to estimate over. Longer is more accurate.
+        #[arg(short = 'n', long, default_value_t = 28)]
+        days: u64,
+        // The last day to estimate over (YYYY-MM-DD), defaulting to today.
+        #[arg(short, long)]
+        date: Option<String>,
+        // The unit of weight change in suggested goals.
+        #[arg(long, default_value = "kg")]
+        unit: WeightUnit,
+    },
+    // Show a journal full-screen, for adding and removing foods.
+    // Starts on today, unless a date (YYYY-MM-DD) is given.
+    Tui {
+        key: Option<String>,
+    },
+    // Show recent changes to foods and journals.
+    // Requires the data directory to be a git repository.
+    Log {
+        #[arg(short = 'n', long, default_value_t = 20)]
+        count: usize,
+    },
+    // Revert the most recent change to foods or journals.
+    // Running it again reverts the change before that.
+    // Without history (see `log`), this is the same as `uneat`.
+    Undo,
 }
 
 #[derive(Parser)]
@@ -63,14 +428,17 @@ fn float1(f: &f32) -> String {
 #[derive(tabled::Tabled)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct NutrientsRow {
-    #[tabled(display_with = "float1")]
+    #[tabled(display_with("Self::show", self, "carb"))]
     pub carb: f32,
-    #[tabled(display_with = "float1")]
+    #[tabled(display_with("Self::show", self, "fat"))]
     pub fat: f32,
-    #[tabled(display_with = "float1")]
+    #[tabled(display_with("Self::show", self, "protein"))]
     pub protein: f32,
-    #[tabled(display_with = "float0")]
+    #[tabled(display_with("Self::show", self, "kcal"))]
     pub kcal: f32,
+    // If set, each nutrient is also shown as a percent of these daily values.
+    #[tabled(skip)]
+    pub dv: Option<Nutrients>,
 }
 
 impl From<Nutrients> for NutrientsRow {
@@ -80,6 +448,37 @@ impl From<Nutrients> for NutrientsRow {
             fat: value.fat,
             protein: value.protein,
             kcal: value.kcal,
+            dv: None,
+        }
+    }
+}
+
+impl NutrientsRow {
+    // Show each nutrient as a percent of the given daily values too, if any.
+    fn with_dv(self, dv: Option<Nutrients>) -> Self {
+        Self { dv, ..self }
+    }
+
+    // Show a nutrient by name, such as "30.0" or "30.0 (11%)" with daily values.
+    fn show(&self, name: &str) -> String {
+        let nutrients = Nutrients {
+            carb: self.carb,
+            fat: self.fat,
+            protein: self.protein,
+            kcal: self.kcal,
+        };
+        let value = nutrients.get(name).unwrap_or_default();
+        let value = match name {
+            "kcal" => float0(&value),
+            _ => float1(&value),
+        };
+        match self
+            .dv
+            .as_ref()
+            .and_then(|dv| nutrients.percent_of(dv).get(name))
+        {
+            Some(percent) => format!("{value} ({percent:.0}%)"),
+            None => value,
         }
     }
 }
@@ -109,12 +508,24 @@ impl FoodRow {
     }
 }
 
+// A row of `food ls`.
+#[derive(tabled::Tabled)]
+struct FoodListRow {
+    #[tabled(inline)]
+    food: FoodRow,
+    aliases: String,
+}
+
 #[derive(tabled::Tabled)]
 struct JournalRow {
+    #[tabled(rename = "#")]
+    index: usize,
+    time: String,
     name: String,
     serving: Serving,
     #[tabled(inline)]
     nutrients: NutrientsRow,
+    note: String,
 }
 
 fn main() -> Result<()> {
@@ -122,62 +533,415 @@ fn main() -> Result<()> {
 
     let args = Args::parse();
     let dirs = xdg::BaseDirectories::new()?;
-    let data = Database::new(&dirs.create_data_directory(APP_NAME)?)?;
+    let data = Database::new(dirs.create_data_directory(APP_NAME)?)?;
 
     match args.command {
-        Command::Eat { food, serving } => eat(&data, food, serving),
+        Command::Eat {
+            repeat: Some(from), ..
+        } => copy_journal(&data, &from, None, &[], true),
+        Command::Eat {
+            meal: Some(meal), ..
+        } => eat_meal(&data, &meal),
+        Command::Eat {
+            foods, quick, note, ..
+        } => match quick.nutrients() {
+            Some(nutrients) => eat_quick(&data, &foods.join(" "), nutrients, note),
+            None => eat(&data, &foods, note),
+        },
+        Command::Drink { args } => drink(&data, &args),
+        Command::Uneat { date } => uneat(&data, date),
         Command::Food { command } => match command {
-            FoodCommand::Edit { key } => edit_food(&data, &key),
-            FoodCommand::Show { key } => show_food(&data, &key),
-            FoodCommand::Search { key, term } => search_food(&data, key, term),
+            FoodCommand::Edit {
+                key,
+                recipe,
+                from_search,
+            } => {
+                if from_search {
+                    search_food(&data, &dirs, SearchArgs::edit(key))
+                } else {
+                    edit_food(&data, &key, recipe)
+                }
+            }
+            FoodCommand::Show { key, dv } => show_food(&data, &key, dv),
+            FoodCommand::Search(args) => search_food(&data, &dirs, args),
             FoodCommand::Ls { term } => list_food(&data, term),
-            FoodCommand::Rm { key } => rm_food(&data, key),
+            FoodCommand::Rm {
+                key,
+                force,
+                snapshot,
+            } => rm_food(&data, key, force, snapshot),
+            FoodCommand::Mv { old, new, dry_run } => mv_food(&data, &old, &new, dry_run),
         },
         Command::Journal { command } => match command {
             JournalCommand::Edit { key } => edit_journal(&data, key),
-            JournalCommand::Show { key } => show_journal(&data, key),
+            JournalCommand::Show { key, dv } => show_journal(&data, key, dv),
+            JournalCommand::Rm { entry, date } => rm_journal(&data, &entry, date),
+            JournalCommand::Set {
+                index,
+                serving,
+                date,
+            } => set_journal(&data, index, &serving, date),
+            JournalCommand::Copy { from, to, entries } => {
+                copy_journal(&data, &from, to, &entries, false)
+            }
+        },
+        Command::Burn { args, date } => burn(&data, &args, date),
+        Command::Activity { command } => match command {
+            ActivityCommand::Edit { key } => edit_activity(&data, &key),
+            ActivityCommand::Ls => list_activity(&data),
+        },
+        Command::Meal { command } => match command {
+            MealCommand::Edit { key } => edit_meal(&data, &key),
+            MealCommand::Show { key } => show_meal(&data, &key),
+            MealCommand::Ls => list_meal(&data),
+            MealCommand::Rm { key } => {
+                data.transaction(&format!("remove meal {key}"), || data.remove::<Meal>(&key))
+            }
+            MealCommand::Save { key, date, entries } => save_meal(&data, &key, date, &entries),
         },
+        Command::Fdc { command } => match command {
+            FdcCommand::Import { path } => import_fdc(&data, path),
+        },
+        Command::Goal { name, value } => goal(&data, name, value),
+        Command::Weigh { weight, date, unit } => weigh(&data, &weight, date, unit),
+        Command::Measure { name, value, date } => measure(&data, &name, &value, date),
+        Command::Report { days, date, unit } => report(&data, days, date, unit),
+        Command::Tdee { days, date, unit } => tdee(&data, days, date, unit),
+        Command::Tui { key } => tui(&data, key),
+        Command::Check => check(&data),
+        Command::Log { count } => history(&data, count),
+        Command::Undo => undo(&data),
     }?;
 
     Ok(())
 }
 
 fn edit_journal(data: &Database, key: Option<String>) -> Result<()> {
-    let date = match key {
-        Some(key) => chrono::NaiveDate::parse_from_str(&key, "%Y-%m-%d")?,
-        None => chrono::Local::now().date_naive(),
-    };
+    let date = parse_date(key)?;
     let journal = data.load_journal(&date)?.unwrap_or_default();
-    let journal = edit(&journal, &data)?;
-    data.save_journal(&date, &journal)
+    let (_, text) = edit(data, &date, &journal)?;
+    let message = format!("edit journal {date}");
+    data.transaction(&message, || {
+        data.save_text::<nosh::Journal>(&date, &text, || message.clone())
+    })
+}
+
+// Parse a journal date (YYYY-MM-DD, today or yesterday), defaulting to today.
+fn parse_date(key: Option<String>) -> Result<chrono::NaiveDate> {
+    let today = chrono::Local::now().date_naive();
+    Ok(match key.as_deref() {
+        None | Some("today") => today,
+        Some("yesterday") => today - chrono::Days::new(1),
+        Some(key) => chrono::NaiveDate::parse_from_str(key, "%Y-%m-%d")?,
+    })
+}
+
+// Pick the entries at the given indexes, or all of them if there are none.
+fn select(entries: Vec<JournalEntry>, indexes: &[usize]) -> Result<Vec<JournalEntry>> {
+    if indexes.is_empty() {
+        return Ok(entries);
+    }
+    let len = entries.len();
+    let mut entries: Vec<_> = entries.into_iter().map(Some).collect();
+    indexes
+        .iter()
+        .map(|&i| {
+            entries
+                .get_mut(i)
+                .with_context(|| format!("No entry {i}, there are {len} entries"))?
+                .take()
+                .with_context(|| format!("Entry {i} was given twice"))
+        })
+        .collect()
 }
 
-fn show_journal(data: &Database, key: Option<String>) -> Result<()> {
-    let date = match key {
-        Some(key) => chrono::NaiveDate::parse_from_str(&key, "%Y-%m-%d")?,
-        None => chrono::Local::now().date_naive(),
+// Copy entries from one journal to another.
+// If eaten_now is set, as when repeating a day, the entries are given the current time,
+// otherwise they keep the times they were eaten.
+fn copy_journal(
+    data: &Database,
+    from: &str,
+    to: Option<String>,
+    entries: &[usize],
+    eaten_now: bool,
+) -> Result<()> {
+    let from = parse_date(Some(from.into()))?;
+    let to = parse_date(to)?;
+    let source = data
+        .load_journal(&from)?
+        .with_context(|| format!("No journal for {from}"))?;
+    let mut entries = select(source.0, entries)?;
+    if eaten_now {
+        entries = entries.into_iter().map(JournalEntry::eaten_now).collect();
+    }
+    let count = entries.len();
+    let mut journal = data.load_journal(&to)?.unwrap_or_default();
+    journal.extend(entries);
+    data.transaction(&format!("copy {from} to {to}"), || {
+        data.save_journal(&to, &journal)
+    })?;
+    println!("Copied {count} entries from {from} to {to}");
+    Ok(())
+}
+
+fn eat_meal(data: &Database, key: &str) -> Result<()> {
+    let meal = data
+        .load_meal(key)?
+        .with_context(|| format!("No meal with key {key:?}"))?;
+    let date = chrono::Local::now().date_naive();
+    let count = meal.0.len();
+    let mut journal = data.load_journal(&date)?.unwrap_or_default();
+    journal.extend(meal.0.into_iter().map(JournalEntry::eaten_now));
+    data.transaction(&format!("eat meal {key} on {date}"), || {
+        data.save_journal(&date, &journal)
+    })?;
+    println!("Added {count} entries from {key} to {date}");
+    Ok(())
+}
+
+fn edit_meal(data: &Database, key: &str) -> Result<()> {
+    let meal = data.load_meal(key)?.unwrap_or_default();
+    let (_, text) = edit(data, key, &meal)?;
+    let message = format!("edit meal {key}");
+    data.transaction(&message, || {
+        data.save_text::<Meal>(key, &text, || message.clone())
+    })
+}
+
+fn show_meal(data: &Database, key: &str) -> Result<()> {
+    let meal = data
+        .load_meal(key)?
+        .with_context(|| format!("No meal with key {key:?}"))?;
+    print_entries(&meal.0, None)
+}
+
+fn burn(data: &Database, args: &[String], date: Option<String>) -> Result<()> {
+    let date = parse_date(date)?;
+    let (key, effort) = match args {
+        [effort] => (nosh::EXERCISE_KEY, effort),
+        [key, effort] => (key.as_str(), effort),
+        _ => bail!("Expected an activity and amount, such as `running 45min`"),
     };
+    let mut entry = ExerciseEntry {
+        key: key.into(),
+        effort: effort.parse()?,
+        kcal: None,
+    };
+    // Record the kcal now, so changing the activity or weight later doesn't change it.
+    let kcal = data.burned(date, &entry)?;
+    entry.kcal = Some(kcal);
+    let message = format!("burn {key} {} on {date}", entry.effort);
+    let mut exercise = data.load_exercise(&date)?.unwrap_or_default();
+    exercise.0.push(entry);
+    data.transaction(&message, || data.save_exercise(&date, &exercise))?;
+    println!("Burned {kcal:.0} kcal on {date}");
+    Ok(())
+}
+
+fn edit_activity(data: &Database, key: &str) -> Result<()> {
+    let activity = data.load_activity(key)?.unwrap_or_default();
+    let (_, text) = edit(data, key, &activity)?;
+    let message = format!("edit activity {key}");
+    data.transaction(&message, || {
+        data.save_text::<Activity>(key, &text, || message.clone())
+    })
+}
+
+fn list_activity(data: &Database) -> Result<()> {
+    for key in data.list_activity()? {
+        println!("{key}");
+    }
+    Ok(())
+}
+
+fn list_meal(data: &Database) -> Result<()> {
+    for key in data.list_meal()? {
+        println!("{key}");
+    }
+    Ok(())
+}
+
+fn save_meal(data: &Database, key: &str, date: Option<String>, entries: &[usize]) -> Result<()> {
+    let date = parse_date(date)?;
+    let journal = data
+        .load_journal(&date)?
+        .with_context(|| format!("No journal for {date}"))?;
+    // A meal may be eaten at any time, so only notes are kept.
+    let entries = select(journal.0, entries)?;
+    let meal = Meal(
+        entries
+            .into_iter()
+            .map(|e| JournalEntry { time: None, ..e })
+            .collect(),
+    );
+    data.transaction(&format!("save meal {key} from {date}"), || {
+        data.save_meal(key, &meal)
+    })?;
+    println!("Saved {} entries from {date} as {key}", meal.0.len());
+    Ok(())
+}
+
+fn uneat(data: &Database, date: Option<String>) -> Result<()> {
+    let date = parse_date(date)?;
+    let mut journal = data.load_journal(&date)?.unwrap_or_default();
+    let Some(entry) = journal.0.pop() else {
+        bail!("Nothing eaten on {date}");
+    };
+    let (key, serving) = (&entry.key, &entry.serving);
+    data.transaction(&format!("uneat {key} {serving} on {date}"), || {
+        data.save_journal(&date, &journal)
+    })?;
+    println!("Removed {key} {serving} from {date}");
+    Ok(())
+}
+
+fn rm_journal(data: &Database, entry: &str, date: Option<String>) -> Result<()> {
+    let date = parse_date(date)?;
+    let mut journal = data.load_journal(&date)?.unwrap_or_default();
+    let (message, removed) = match entry.parse::<usize>() {
+        Ok(index) => {
+            let len = journal.0.len();
+            anyhow::ensure!(
+                index < len,
+                "No entry {index} on {date}, which has {len} entries"
+            );
+            let entry = journal.0.remove(index);
+            let message = format!("uneat {} {} on {date}", entry.key, entry.serving);
+            (message, vec![entry])
+        }
+        Err(_) => {
+            let (removed, kept) = journal.0.into_iter().partition(|e| e.key == entry);
+            journal.0 = kept;
+            (format!("uneat {entry} on {date}"), removed)
+        }
+    };
+    if removed.is_empty() {
+        bail!("No entries for {entry:?} on {date}");
+    }
+    data.transaction(&message, || data.save_journal(&date, &journal))?;
+    for entry in removed {
+        println!("Removed {} {} from {date}", entry.key, entry.serving);
+    }
+    Ok(())
+}
+
+fn set_journal(data: &Database, index: usize, serving: &str, date: Option<String>) -> Result<()> {
+    let date = parse_date(date)?;
+    let serving: Serving = serving.parse()?;
+    let mut journal = data.load_journal(&date)?.unwrap_or_default();
+    let len = journal.0.len();
+    let Some(entry) = journal.0.get_mut(index) else {
+        bail!("No entry {index} on {date}, which has {len} entries");
+    };
+    if let Err(err) = entry.food.serve(&serving) {
+        bail!("Invalid serving: {err:?}");
+    };
+    let key = entry.key.clone();
+    let message = format!("set {key} to {serving} on {date}");
+    entry.serving = serving.clone();
+    data.transaction(&message, || data.save_journal(&date, &journal))?;
+    println!("Set {key} to {serving} on {date}");
+    Ok(())
+}
+
+fn show_journal(data: &Database, key: Option<String>, dv: bool) -> Result<()> {
+    let date = parse_date(key)?;
     let journal = data.load_journal(&date)?.unwrap_or_default();
-    let rows: Result<Vec<_>> = journal
-        .0
+    let goals = data.load_goals()?;
+    print_entries(&journal.0, dv.then(|| goals.daily_values()))?;
+    println!("Fluid {:.0} of {:.0} ml", journal.fluid()?, goals.fluid());
+    if let Some(exercise) = data.load_exercise(&date)? {
+        print_exercise(data, date, &journal, &exercise)?;
+    }
+    Ok(())
+}
+
+// A row of exercise in `journal show`.
+#[derive(tabled::Tabled)]
+struct ExerciseRow {
+    activity: String,
+    amount: String,
+    // None if it can't be worked out, such as when the activity was removed.
+    #[tabled(display_with = "kcal_or_unknown")]
+    kcal: Option<f32>,
+}
+
+fn kcal_or_unknown(kcal: &Option<f32>) -> String {
+    kcal.as_ref().map(float0).unwrap_or("?".into())
+}
+
+// Print the exercise done on a day, and the kcal eaten net of it.
+fn print_exercise(
+    data: &Database,
+    date: chrono::NaiveDate,
+    journal: &nosh::Journal,
+    exercise: &nosh::Exercise,
+) -> Result<()> {
+    let mut rows = vec![];
+    for entry in &exercise.0 {
+        let name = match data.load_activity(&entry.key) {
+            Ok(Some(activity)) if !activity.name.is_empty() => activity.name,
+            _ => entry.key.clone(),
+        };
+        let kcal = match data.burned(date, entry) {
+            Ok(kcal) => Some(kcal),
+            Err(err) => {
+                log::warn!("Unknown kcal burned by {}: {err:#}", entry.key);
+                None
+            }
+        };
+        rows.push(ExerciseRow {
+            activity: name,
+            amount: entry.effort.to_string(),
+            kcal,
+        });
+    }
+    let eaten = journal.nutrients()?.kcal;
+    let burned: f32 = rows.iter().filter_map(|r| r.kcal).sum();
+    println!("{}", Table::new(rows).with(Style::sharp()));
+    println!(
+        "Eaten {eaten:.0} kcal, burned {burned:.0} kcal, net {:.0} kcal",
+        eaten - burned
+    );
+    Ok(())
+}
+
+// Print a table of journal entries, with their total nutrients,
+// and their percent of the daily values if given.
+fn print_entries(entries: &[JournalEntry], dv: Option<Nutrients>) -> Result<()> {
+    let rows: Result<Vec<_>> = entries
         .iter()
-        .map(|entry| {
+        .enumerate()
+        .map(|(index, entry)| {
             Ok(JournalRow {
+                index,
+                time: entry
+                    .time
+                    .map(|t| t.format(TIME_FORMAT).to_string())
+                    .unwrap_or_default(),
                 serving: entry.serving.clone(),
-                nutrients: entry.food.serve(&entry.serving)?.into(),
+                nutrients: NutrientsRow::from(entry.food.serve(&entry.serving)?).with_dv(dv),
                 name: entry.food.name.clone(),
+                note: entry.note.clone().unwrap_or_default(),
             })
         })
         .collect();
     let rows = rows?;
-    let total: NutrientsRow = journal.nutrients()?.into();
+    let mut total = Nutrients::default();
+    for entry in entries {
+        total += entry.food.serve(&entry.serving)?;
+    }
+    let total = NutrientsRow::from(total).with_dv(dv);
     let mut total = Table::new([[
+        "".to_string(),
+        "".to_string(),
         "Total".to_string(),
         "".to_string(),
-        format!("{:.1}", total.carb),
-        format!("{:.1}", total.fat),
-        format!("{:.1}", total.protein),
-        format!("{:.0}", total.kcal),
+        total.show("carb"),
+        total.show("fat"),
+        total.show("protein"),
+        total.show("kcal"),
+        "".to_string(),
     ]]);
     total.with(ColumnNames::default());
 
@@ -197,62 +961,474 @@ fn show_journal(data: &Database, key: Option<String>) -> Result<()> {
     Ok(())
 }
 
-fn eat(data: &Database, key: String, serving: Option<String>) -> Result<()> {
-    let Some(food) = data.load_food(&key)? else {
-        bail!("No food with key {key:?}");
+fn check(data: &Database) -> Result<()> {
+    let problems = data.check()?;
+    for problem in &problems {
+        println!("{problem}");
+    }
+    match problems.len() {
+        0 => Ok(()),
+        1 => bail!("Found 1 problem"),
+        n => bail!("Found {n} problems"),
+    }
+}
+
+// The git repository of the data directory, needed to show or undo changes.
+fn git(data: &Database) -> Result<&nosh::git::Git> {
+    data.git().with_context(|| {
+        format!(
+            "History is only kept if the data directory is a git repository, \
+             run `git init {}` to enable it",
+            data.dir().display()
+        )
+    })
+}
+
+fn history(data: &Database, count: usize) -> Result<()> {
+    for commit in git(data)?.log(count)? {
+        println!("{} {} {}", commit.id, commit.date, commit.message);
+    }
+    Ok(())
+}
+
+fn undo(data: &Database) -> Result<()> {
+    let Some(git) = data.git() else {
+        return uneat(data, None);
     };
-    let serving = match serving {
-        Some(s) => s.parse()?,
-        None => Serving::default(),
+    match git.undo()? {
+        Some(commit) => println!("Undid: {}", commit.message),
+        None => println!("Nothing to undo"),
+    }
+    Ok(())
+}
+
+fn weigh(data: &Database, weight: &str, date: Option<String>, unit: WeightUnit) -> Result<()> {
+    let date = parse_date(date)?;
+    let weight = Weight::parse(weight, unit)?;
+    let mut body = data.load_body(&date)?.unwrap_or_default();
+    body.weight = Some(weight);
+    data.transaction(&format!("weigh {weight} on {date}"), || {
+        data.save_body(&date, &body)
+    })
+}
+
+fn measure(data: &Database, name: &str, value: &str, date: Option<String>) -> Result<()> {
+    let date = parse_date(date)?;
+    if name == "weight" {
+        bail!("Use `nosh weigh` to record weight");
+    }
+    let mut body = data.load_body(&date)?.unwrap_or_default();
+    body.measure(name, value);
+    data.transaction(&format!("measure {name} {value} on {date}"), || {
+        data.save_body(&date, &body)
+    })
+}
+
+// A row of `report`, for one day.
+#[derive(tabled::Tabled)]
+struct ReportRow {
+    date: chrono::NaiveDate,
+    carb: String,
+    fat: String,
+    protein: String,
+    kcal: String,
+    weight: String,
+    trend: String,
+}
+
+// Return the first day of a period of `days` ending on `end`, and the first day
+// needed for the weight trend of that day, which averages the days before it.
+fn period(end: chrono::NaiveDate, days: u64) -> Result<(chrono::NaiveDate, chrono::NaiveDate)> {
+    let start = end.checked_sub_days(chrono::Days::new(days.saturating_sub(1)));
+    let first = start
+        .and_then(|start| start.checked_sub_days(chrono::Days::new(nosh::TREND_DAYS as u64 - 1)));
+    match (start, first) {
+        (Some(start), Some(first)) => Ok((start, first)),
+        _ => bail!("Too many days: {days}"),
+    }
+}
+
+fn report(data: &Database, days: u64, date: Option<String>, unit: WeightUnit) -> Result<()> {
+    let end = parse_date(date)?;
+    let (start, first) = period(end, days)?;
+    let weights = data.weights(first, end, unit)?;
+
+    let mut rows = vec![];
+    for day in start.iter_days().take_while(|d| *d <= end) {
+        let nutrients = match data.load_journal(&day)? {
+            Some(journal) => Some(NutrientsRow::from(journal.nutrients()?)),
+            None => None,
+        };
+        let nutrient =
+            |f: fn(&NutrientsRow) -> String| nutrients.as_ref().map(f).unwrap_or_default();
+        let weight = weights.iter().find(|(d, _)| *d == day);
+        rows.push(ReportRow {
+            date: day,
+            carb: nutrient(|n| float1(&n.carb)),
+            fat: nutrient(|n| float1(&n.fat)),
+            protein: nutrient(|n| float1(&n.protein)),
+            kcal: nutrient(|n| float0(&n.kcal)),
+            weight: weight
+                .map(|(_, w)| format!("{w:.1} {unit}"))
+                .unwrap_or_default(),
+            trend: nosh::trend(&weights, day)
+                .map(|w| format!("{w:.1} {unit}"))
+                .unwrap_or_default(),
+        });
+    }
+    println!("{}", Table::new(rows).with(Style::sharp()));
+    Ok(())
+}
+
+// A suggested kcal goal of `tdee`.
+#[derive(tabled::Tabled)]
+struct GoalRow {
+    goal: String,
+    #[tabled(display_with = "float0")]
+    kcal: f32,
+}
+
+fn tdee(data: &Database, days: u64, date: Option<String>, unit: WeightUnit) -> Result<()> {
+    let end = parse_date(date)?;
+    let (start, first) = period(end, days)?;
+    let intake = data.intake(start, end)?;
+    let weights = data.weights(first, end, WeightUnit::Kg)?;
+    let Some(tdee) = nosh::Tdee::estimate(&intake, &weights, start, end) else {
+        bail!(
+            "Not enough data to estimate TDEE from {start} to {end}, \
+             it needs journals, and weights in the week before {start} and {end}"
+        );
     };
-    if let Err(err) = food.serve(&serving) {
-        bail!("Invalid serving: {err:?}");
+
+    let per_kg = Weight {
+        value: 1.0,
+        unit: WeightUnit::Kg,
+    }
+    .to(unit);
+    println!("Estimated TDEE: {:.0} kcal/day", tdee.kcal);
+    println!(
+        "From {} days eaten, averaging {:.0} kcal, while weight changed by {:+.2} {unit}/week",
+        tdee.days,
+        tdee.intake,
+        tdee.change * 7.0 * per_kg
+    );
+
+    // Rates are round numbers in the chosen unit.
+    let rates = match unit {
+        WeightUnit::Kg => [-0.5, -0.25, 0.0, 0.25],
+        WeightUnit::Lb => [-1.0, -0.5, 0.0, 0.5],
     };
+    let rows = rates.map(|rate: f32| GoalRow {
+        goal: match rate {
+            0.0 => "maintain".to_string(),
+            r if r < 0.0 => format!("lose {} {unit}/week", -r),
+            r => format!("gain {r} {unit}/week"),
+        },
+        kcal: tdee.goal(rate / per_kg),
+    });
+    println!("{}", Table::new(rows).with(Style::sharp()));
+    Ok(())
+}
+
+fn tui(data: &Database, key: Option<String>) -> Result<()> {
+    let date = parse_date(key)?;
+    nosh::tui::run(data, date)
+}
+
+// Find a food by key, falling back to a fuzzy search.
+// If the search finds several foods, the user picks one.
+fn resolve_food(data: &Database, key: &str) -> Result<(String, Food)> {
+    if let Some(food) = data.load_food(key)? {
+        let key = data.resolve_alias(key)?.unwrap_or(key.into());
+        return Ok((key, food));
+    }
+    let found = data.find_food(key)?;
+    let key = match found.as_slice() {
+        [] => return Err(data.food_not_found(key)),
+        // Without a terminal to confirm, don't guess which food was meant.
+        _ if !std::io::stdin().is_terminal() => return Err(data.food_not_found(key)),
+        [(found, name)] => {
+            let res = prompt(&format!(
+                "No food with key {key:?}, use {found:?} ({name}) [Y/n]? "
+            ))?;
+            if res.eq_ignore_ascii_case("n") {
+                bail!("No food selected");
+            }
+            found.clone()
+        }
+        _ => {
+            println!("No food with key {key:?}, did you mean:");
+            let found: Vec<_> = found.into_iter().take(10).collect();
+            for (i, (key, name)) in found.iter().enumerate() {
+                println!("{i}: {key} ({name})");
+            }
+            let res = prompt(&format!("[0-{}], enter to cancel? ", found.len() - 1))?;
+            if res.is_empty() {
+                bail!("No food selected");
+            }
+            let idx: usize = res.parse()?;
+            found.into_iter().nth(idx).context("Index out of range")?.0
+        }
+    };
+    let food = data
+        .load_food(&key)?
+        .with_context(|| format!("No food with key {key:?}"))?;
+    Ok((key, food))
+}
+
+// Add a food to today's journal without a food file, by saving it in the journal.
+fn eat_quick(
+    data: &Database,
+    name: &str,
+    nutrients: Nutrients,
+    note: Option<String>,
+) -> Result<()> {
+    let date = chrono::Local::now().date_naive();
+    let mut journal = data.load_journal(&date)?.unwrap_or_default();
+
+    // The key can't be used by another food, or those entries would use this one.
+    let base: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let base = match base.trim_matches('_') {
+        "" => "quick",
+        base => base,
+    };
+    let mut key = base.to_string();
+    for i in 2.. {
+        if journal.0.iter().all(|e| e.key != key) && data.load_food(&key)?.is_none() {
+            break;
+        }
+        key = format!("{base}_{i}");
+    }
+
+    log::debug!("Adding {name} as {key} with {nutrients:?} to {date:?}");
+    let message = format!("eat {key} on {date}");
+    journal.0.push(
+        JournalEntry {
+            key,
+            food: Food {
+                name: name.into(),
+                spec: nosh::FoodSpec::Nutrients(nutrients),
+                ..Default::default()
+            },
+            inline: true,
+            note,
+            ..Default::default()
+        }
+        .eaten_now(),
+    );
+    data.transaction(&message, || data.save_journal(&date, &journal))
+}
+
+// Split eat arguments into foods and their servings.
+// A serving applies to the food before it, so "oats 0.5c banana" is two foods.
+fn parse_foods(data: &Database, args: &[String]) -> Result<Vec<(String, Serving)>> {
+    // A food key like "7up" looks like a serving, so foods take precedence.
+    let is_serving = |arg: &str| -> Result<bool> {
+        Ok(arg.parse::<Serving>().is_ok() && data.load_food(arg)?.is_none())
+    };
+    let mut res: Vec<(String, Option<Serving>)> = vec![];
+    for arg in args {
+        if let Some((key, serving)) = arg.split_once('=') {
+            let serving = serving
+                .parse()
+                .with_context(|| format!("Invalid serving for {key}"))?;
+            res.push((key.trim().into(), Some(serving)));
+            continue;
+        }
+        let serving = match is_serving(arg)? {
+            true => Some(arg.parse()?),
+            false => None,
+        };
+        match (res.last_mut(), serving) {
+            (Some((_, last @ None)), Some(serving)) => *last = Some(serving),
+            (_, Some(_)) => bail!("Serving {arg:?} must follow a food"),
+            (_, None) => res.push((arg.clone(), None)),
+        }
+    }
+    Ok(res
+        .into_iter()
+        .map(|(key, serving)| (key, serving.unwrap_or_default()))
+        .collect())
+}
+
+fn eat(data: &Database, args: &[String], note: Option<String>) -> Result<()> {
+    // Resolve and check every food before changing the journal, so it's all or nothing.
+    let mut entries = vec![];
+    for (key, serving) in parse_foods(data, args)? {
+        let (key, food) = resolve_food(data, &key)?;
+        if let Err(err) = food.serve(&serving) {
+            bail!("Invalid serving for {key}: {err:?}");
+        };
+        entries.push(
+            JournalEntry {
+                key,
+                serving,
+                food,
+                note: note.clone(),
+                ..Default::default()
+            }
+            .eaten_now(),
+        );
+    }
 
     let date = chrono::Local::now().date_naive();
-    log::debug!("Adding food={key} serving={serving} to {date:?}");
+    let foods: Vec<_> = entries
+        .iter()
+        .map(|e| format!("{} {}", e.key, e.serving))
+        .collect();
+    log::debug!("Adding {foods:?} to {date:?}");
 
+    let message = format!("eat {} on {date}", foods.join(", "));
     let mut journal = data.load_journal(&date)?.unwrap_or_default();
-    journal.0.push(JournalEntry { key, serving, food });
-    data.save_journal(&date, &journal)
+    journal.extend(entries);
+    data.transaction(&message, || data.save_journal(&date, &journal))
 }
 
-fn edit<T: nosh::Data + std::fmt::Debug>(orig: &T, data: &Database) -> Result<T> {
-    let mut tmp = tempfile::Builder::new().suffix(".txt").tempfile()?;
-    orig.save(&mut std::io::BufWriter::new(&tmp))?;
-    tmp.flush()?;
-    log::debug!("Wrote {orig:?} to {tmp:?}");
+// The serving of a food to drink some ml of it.
+// A volume is kept as given if the food has that unit, or else converted to a
+// unit of volume it does have, such as 250ml to 1.04 cup. Failing that, the
+// serving is how many of the food's servings have that much fluid.
+fn drink_serving(food: &Food, serving: Serving, ml: f32, fluid: f32) -> Serving {
+    if food.portion(&serving).is_ok() {
+        return serving;
+    }
+    let round = |size: f32| (size * 100.0).round() / 100.0;
+    for (unit, _) in &food.servings {
+        let one = Serving {
+            size: 1.0,
+            unit: Some(unit.clone()),
+        };
+        if let Some(per_unit) = one.ml() {
+            return Serving {
+                size: round(ml / per_unit),
+                unit: one.unit,
+            };
+        }
+    }
+    Serving {
+        size: round(ml / fluid),
+        unit: None,
+    }
+}
 
-    let editor = std::env::var("EDITOR").context("EDITOR not set")?;
-    let editor = which::which(editor)?;
-    let mut cmd = std::process::Command::new(editor);
-    cmd.arg(tmp.path())
-        .stdin(std::process::Stdio::inherit())
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit());
-    log::debug!("Running {cmd:?}");
+fn drink(data: &Database, args: &[String]) -> Result<()> {
+    let (key, volume) = match args {
+        [volume] => ("water", volume),
+        [key, volume] => (key.as_str(), volume),
+        _ => bail!("Expected a drink and volume, such as `milk 250ml`"),
+    };
+    let serving: Serving = volume.parse()?;
+    let Some(ml) = serving.ml() else {
+        bail!("Expected a volume, such as 500ml, not {volume:?}");
+    };
+    let entry = match args {
+        // Water needn't be in the database, as it has no nutrients.
+        [_] if data.load_food(key)?.is_none() => JournalEntry {
+            key: key.into(),
+            serving: Serving {
+                size: ml,
+                unit: Some("ml".into()),
+            },
+            food: Food {
+                name: "Water".into(),
+                servings: vec![("ml".into(), 1.0)],
+                fluid: Some(1.0),
+                ..Default::default()
+            },
+            inline: true,
+            ..Default::default()
+        },
+        _ => {
+            let (key, food) = resolve_food(data, key)?;
+            let fluid = food.fluid(&Serving::default())?;
+            if fluid <= 0.0 {
+                bail!("{key} has no fluid, set it with `nosh food edit {key}`");
+            }
+            JournalEntry {
+                key,
+                serving: drink_serving(&food, serving, ml, fluid),
+                food,
+                ..Default::default()
+            }
+        }
+    }
+    .eaten_now();
 
-    let status = cmd.spawn()?.wait()?;
-    anyhow::ensure!(status.success(), "Editor exited with code: {status:?}");
+    let date = chrono::Local::now().date_naive();
+    let message = format!("drink {} {} on {date}", entry.key, entry.serving);
+    let mut journal = data.load_journal(&date)?.unwrap_or_default();
+    journal.extend([entry]);
+    data.transaction(&message, || data.save_journal(&date, &journal))?;
+    println!(
+        "Fluid {:.0} of {:.0} ml",
+        journal.fluid()?,
+        data.load_goals()?.fluid()
+    );
+    Ok(())
+}
 
-    let file = fs::File::open(tmp.path())?;
-    let reader = std::io::BufReader::new(file);
-    let new = T::load(reader, |key| data.load_food(key))?;
-    log::debug!("Parsed: {new:?}");
-    Ok(new)
+fn goal(data: &Database, name: Option<String>, value: Option<String>) -> Result<()> {
+    let mut goals = data.load_goals()?;
+    let (Some(name), Some(value)) = (name, value) else {
+        println!("fluid = {} ml", goals.fluid());
+        let dv = goals.daily_values();
+        for (name, _) in nosh::DAILY_VALUES {
+            println!("{name} = {}", dv.get(name).unwrap_or_default());
+        }
+        return Ok(());
+    };
+    goals.set(&name, &value)?;
+    data.transaction(&format!("set goal {name} to {value}"), || {
+        data.save_goals(&goals)
+    })
 }
 
-fn edit_food(data: &Database, key: &str) -> Result<()> {
-    let food = data.load_food(key)?.unwrap_or_default();
-    let food = edit(&food, &data)?;
-    data.save_food(key, &food)
+// Edit an item in $EDITOR, offering to edit it again if it has problems.
+// Returns the item and the edited text, which keeps the user's comments.
+fn edit<T: nosh::Data + std::fmt::Debug>(
+    data: &Database,
+    key: &T::Key,
+    orig: &T,
+) -> Result<(T, String)> {
+    nosh::edit::edit(data, key, orig, nosh::edit::open_editor, |problems| {
+        if !std::io::stdin().is_terminal() {
+            return Ok(false);
+        }
+        eprintln!("{problems}");
+        Ok(!prompt("Edit again [Y/n]? ")?.eq_ignore_ascii_case("n"))
+    })
 }
 
-fn show_food(data: &Database, key: &str) -> Result<()> {
-    let Some(food) = data.load_food(key)? else {
-        bail!("No food with key {key:?}");
+fn edit_food(data: &Database, key: &str, recipe: bool) -> Result<()> {
+    if let Some(other) = data.resolve_alias(key)? {
+        bail!("{key:?} is an alias of {other:?}, use `nosh food edit {other}` instead");
+    }
+    let food = match data.load_food(key)? {
+        Some(_) if recipe => bail!("Food with key {key} already exists"),
+        Some(food) => food,
+        None if recipe => Food {
+            spec: nosh::FoodSpec::Ingredients(vec![]),
+            ..Default::default()
+        },
+        None => Food::default(),
     };
-    let food = FoodRow::new(key, &food);
+    let (_, text) = edit(data, key, &food)?;
+    let message = format!("edit food {key}");
+    data.transaction(&message, || {
+        data.save_text::<Food>(key, &text, || message.clone())
+    })
+}
+
+fn show_food(data: &Database, key: &str, dv: bool) -> Result<()> {
+    let (key, food) = resolve_food(data, key)?;
+    let mut food = FoodRow::new(&key, &food);
+    if dv {
+        food.nutrients.dv = Some(data.load_goals()?.daily_values());
+    }
     let mut table = Table::new(std::iter::once(food));
     println!("{}", table.with(Style::sharp()));
     Ok(())
@@ -264,7 +1440,7 @@ fn list_food(data: &Database, pattern: Option<String>) -> Result<()> {
     let items = data.list_food()?;
     let mut items: Vec<_> = items
         .filter_map(|x| match x {
-            Ok(key) if key.contains(&pattern) => match data.load_food(&key) {
+            Ok(key) => match data.load_food(&key) {
                 Ok(Some(food)) => Some((key, food)),
                 Ok(None) => {
                     // Should be there, as we just listed it.
@@ -277,18 +1453,25 @@ fn list_food(data: &Database, pattern: Option<String>) -> Result<()> {
                     None
                 }
             },
-            Ok(key) => {
-                log::trace!("Food '{key}' does not match '{pattern}'");
-                None
-            }
             Err(err) => {
                 log::error!("Failed to list food: {err:?}");
                 None
             }
         })
-        .map(|(key, food)| FoodRow::new(&key, &food))
+        .filter(|(key, food)| {
+            let matches =
+                key.contains(&pattern) || food.aliases.iter().any(|a| a.contains(&pattern));
+            if !matches {
+                log::trace!("Food '{key}' does not match '{pattern}'");
+            }
+            matches
+        })
+        .map(|(key, food)| FoodListRow {
+            food: FoodRow::new(&key, &food),
+            aliases: food.aliases.join(", "),
+        })
         .collect();
-    items.sort_by(|a, b| a.key.cmp(&b.key));
+    items.sort_by(|a, b| a.food.key.cmp(&b.food.key));
     if !items.is_empty() {
         let mut table = Table::new(items);
         println!("{}", table.with(Style::sharp()));
@@ -296,78 +1479,294 @@ fn list_food(data: &Database, pattern: Option<String>) -> Result<()> {
     Ok(())
 }
 
-fn rm_food(data: &Database, key: String) -> Result<()> {
-    data.remove::<Food>(&key)
+fn rm_food(data: &Database, key: String, force: bool, snapshot: bool) -> Result<()> {
+    data.transaction(&format!("remove food {key}"), || {
+        remove_food(data, &key, force, snapshot)
+    })
+}
+
+// Check that a food can be removed before changing anything,
+// so journals aren't snapshotted if it's still used by a recipe.
+fn remove_food(data: &Database, key: &str, force: bool, snapshot: bool) -> Result<()> {
+    let mut refs = data.find_references(key)?;
+    if snapshot {
+        // Journals won't refer to the food once it's copied into them.
+        refs.retain(|(path, _)| !path.starts_with(<nosh::Journal as nosh::Data>::DIR));
+    }
+    if !refs.is_empty() {
+        let refs: Vec<_> = refs
+            .iter()
+            .map(|(path, line)| format!("  {}:{line}", path.display()))
+            .collect();
+        let refs = refs.join("\n");
+        if !force {
+            let hint = match snapshot {
+                true => "Use --force to remove it anyway",
+                false => "Use --snapshot to copy it into journals, or --force to remove it anyway",
+            };
+            bail!("{key:?} is used by:\n{refs}\n{hint}");
+        }
+        log::warn!("Removing {key:?}, which is used by:\n{refs}");
+    }
+    if snapshot {
+        for date in data.snapshot_food(key)? {
+            println!("Copied {key} into journal {date}");
+        }
+    }
+    data.remove::<Food>(key)
+}
+
+fn mv_food(data: &Database, old: &str, new: &str, dry_run: bool) -> Result<()> {
+    let paths = data.rename_food(old, new, dry_run)?;
+    if dry_run {
+        for path in paths {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
 }
 
-fn search_food(data: &Database, key: String, term: Option<String>) -> Result<()> {
-    if data.load_food(&key)?.is_some() {
+fn search_food(data: &Database, dirs: &xdg::BaseDirectories, args: SearchArgs) -> Result<()> {
+    let SearchArgs {
+        key,
+        term,
+        provider,
+        offline,
+        timeout,
+        retries,
+        json,
+        pick,
+        first,
+        edit: edit_first,
+    } = args;
+    let pick = if first { Some(0) } else { pick };
+
+    if !json && data.load_food(&key)?.is_some() {
         bail!("Food with key {key} already exists");
     }
 
     let term = term.unwrap_or(key.clone());
 
-    let mut search = nosh::Search {
-        term: &term,
-        ..Default::default()
-    };
+    let mut page_size = nosh::Search::default().page_size;
 
     // Show only as many results as will fit on screen.
     if let Some((_, Height(h))) = terminal_size() {
         log::debug!("Terminal height is {h}");
-        // Subtract 6 to leave room for headers/footers.
-        search.page_size = h.saturating_sub(6) as usize;
+        // Subtract 7 to leave room for headers/footers.
+        page_size = h.saturating_sub(7) as usize;
     } else {
         log::warn!("Unable to get terminal size");
     }
 
     // This is mostly here to allow injecting a url for testing.
     let url = std::env::var("NOSH_SEARCH_URL").ok();
-    if let Some(url) = url.as_ref() {
-        search.url = &url;
+
+    let mut client = nosh::http::Client::new(std::time::Duration::from_secs(timeout))?;
+    client.retries = retries;
+    client.cache_dir = Some(dirs.create_cache_directory(APP_NAME)?);
+
+    let index;
+    let search: Box<dyn nosh::Provider> = match (offline, provider) {
+        (true, _) => {
+            index = data
+                .load_index()?
+                .context("No offline index, import one with `nosh fdc import`")?;
+            Box::new(nosh::OfflineSearch {
+                index: &index,
+                page_size,
+            })
+        }
+        (false, Provider::Fdc) => {
+            let mut search = nosh::Search {
+                page_size,
+                client: &client,
+                ..Default::default()
+            };
+            if let Some(url) = url.as_ref() {
+                search.url = url;
+            };
+            Box::new(search)
+        }
+        (false, Provider::Off) => {
+            let mut search = nosh::OpenFoodFacts {
+                page_size,
+                client: &client,
+                ..Default::default()
+            };
+            if let Some(url) = url.as_ref() {
+                search.url = url;
+            };
+            Box::new(search)
+        }
     };
 
+    let mut term = term;
+    let mut page = 1;
+    // The previous term and page, to return to if a refined search finds nothing.
+    let mut prev: Option<(String, usize)> = None;
     loop {
-        let foods = search.next_page()?;
-        let foods: Vec<_> = foods.iter().collect();
+        let res = search.search(&term, page)?;
+        let last_page = res.total_pages.max(1);
+        let mut hits = res.hits;
 
-        if foods.is_empty() {
-            bail!("Found no foods matching '{term}'");
+        if hits.is_empty() {
+            let Some((t, p)) = prev.take() else {
+                bail!("Found no foods matching '{term}'");
+            };
+            println!("Found no foods matching '{term}'");
+            (term, page) = (t, p);
+            continue;
         }
 
-        let table: Vec<_> = foods
+        if json {
+            let foods: Vec<_> = hits
+                .iter()
+                .enumerate()
+                .map(|(i, hit)| FoodJson::new(i, hit))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&foods)?);
+            return Ok(());
+        }
+
+        let add = |food: Food| -> Result<()> {
+            let message = format!("add food {key}");
+            let food = if edit_first {
+                let (food, text) = edit(data, key.as_str(), &food)?;
+                data.transaction(&message, || {
+                    data.save_text::<Food>(key.as_str(), &text, || message.clone())
+                })?;
+                food
+            } else {
+                data.transaction(&message, || data.save_food(key.as_str(), &food))?;
+                food
+            };
+            println!("Added '{}' as {key}", food.name);
+            Ok(())
+        };
+
+        if let Some(idx) = pick {
+            anyhow::ensure!(idx < hits.len(), "Index out of range");
+            return add(hits.swap_remove(idx).food);
+        }
+
+        let table: Vec<_> = hits
             .iter()
             .enumerate()
-            .map(|(i, food)| FoodRow::new(&i.to_string(), food))
+            .map(|(i, hit)| FoodRow::new(&i.to_string(), &hit.food))
             .collect();
 
         let table = Table::new(&table).with(Style::sharp()).to_string();
+        println!(
+            "'{term}': page {} of {}, {} hits",
+            res.page, res.total_pages, res.total_hits
+        );
         println!("{table}");
 
-        print!("\n[0-{}],(n)ext,(q)uit? ", foods.len().saturating_sub(1));
-        std::io::stdout().flush()?;
+        if !std::io::stdin().is_terminal() {
+            log::debug!("Not prompting as stdin is not a terminal");
+            return Ok(());
+        }
 
-        let mut res = String::new();
-        std::io::stdin().read_line(&mut res)?;
-        let res = res.trim();
+        let input = prompt(&format!(
+            "\n[0-{}],(n)ext,(p)rev,(g)oto <page>,(s)earch <term>,(d)etails <index>,(q)uit? ",
+            hits.len().saturating_sub(1)
+        ))?;
 
-        if res.is_empty() {
+        if input.is_empty() {
             log::debug!("Empty response, not adding any food");
             return Ok(());
         }
 
-        if res.starts_with("q") {
-            log::debug!("Quit requested, not adding any food");
-            return Ok(());
-        } else if res.starts_with("n") {
-            log::debug!("Getting next page of results");
-            continue;
+        let (cmd, arg) = match input.split_once(char::is_whitespace) {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (input.as_str(), ""),
+        };
+        match cmd {
+            "q" => {
+                log::debug!("Quit requested, not adding any food");
+                return Ok(());
+            }
+            "n" if page < last_page => page += 1,
+            "n" => println!("Already on the last page"),
+            "p" if page > 1 => page -= 1,
+            "p" => println!("Already on the first page"),
+            "g" => match arg.parse() {
+                Ok(n) if n >= 1 && n <= last_page => page = n,
+                _ => println!("Expected a page from 1 to {last_page}"),
+            },
+            "s" if !arg.is_empty() => {
+                prev = Some((std::mem::replace(&mut term, arg.to_string()), page));
+                page = 1;
+            }
+            "s" => println!("Expected a search term"),
+            "d" => match arg.parse::<usize>().ok().and_then(|i| hits.get(i)) {
+                Some(hit) => {
+                    print_details(hit);
+                    prompt("\nPress enter to continue")?;
+                }
+                None => println!("Expected an index from 0 to {}", hits.len() - 1),
+            },
+            _ => {
+                let idx: usize = input.parse()?;
+                anyhow::ensure!(idx < hits.len(), "Index out of range");
+                let mut food = hits.swap_remove(idx).food;
+
+                let servings = FoodRow::new("", &food).servings;
+                let size = prompt(&format!(
+                    "Serving size (e.g. 30g), or enter to keep [{servings}]? "
+                ))?;
+                if !size.is_empty() {
+                    food.resize(&size.parse()?)?;
+                }
+                return add(food);
+            }
         }
+    }
+}
 
-        let idx: usize = res.parse()?;
-        let food = foods.get(idx).ok_or(anyhow!("Index out of range"))?;
-        data.save_food(key.as_str(), food)?;
-        println!("Added '{}' as {key}", food.name);
-        return Ok(());
+// Print a message and read a line from stdin.
+fn prompt(msg: &str) -> Result<String> {
+    print!("{msg}");
+    std::io::stdout().flush()?;
+    let mut res = String::new();
+    std::io::stdin().read_line(&mut res)?;
+    Ok(res.trim().to_string())
+}
+
+// Print everything known about a search result.
+fn print_details(hit: &nosh::Hit) {
+    let food = &hit.food;
+    let row = FoodRow::new("", food);
+    println!("\n{}", food.name);
+    if let Some(brand) = &hit.details.brand {
+        println!("Brand: {brand}");
+    }
+    if let Some(barcode) = &food.barcode {
+        println!("Barcode: {barcode}");
+    }
+    println!("Servings: {}", row.servings);
+    if let Some(ingredients) = &hit.details.ingredients {
+        println!("Ingredients: {ingredients}");
+    }
+    let nutrients: Vec<_> = hit
+        .details
+        .nutrients
+        .iter()
+        .map(|(name, amount, unit)| [name.clone(), format!("{amount}"), unit.clone()])
+        .collect();
+    if !nutrients.is_empty() {
+        let mut table = Table::new(nutrients);
+        table.with(ColumnNames::new(["nutrient (per 100g)", "amount", "unit"]));
+        println!("{}", table.with(Style::sharp()));
     }
 }
+
+fn import_fdc(data: &Database, path: PathBuf) -> Result<()> {
+    let new = nosh::Index::import(&path)?;
+    let count = new.len();
+    let mut index = data.load_index()?.unwrap_or_default();
+    index.merge(new);
+    data.save_index(&index)?;
+    println!("Imported {count} foods, {} total", index.len());
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::{check, journal, Data, Database, Food, Journal, JournalEntry};
+use anyhow::Result;
+use std::path::Path;
+
+// Meal is a named list of foods that are often eaten together,
+// such as a usual breakfast, which can be added to a journal at once.
+// It uses the same format as a journal, for example:
+// ```
+// oats = 0.5 cup
+// banana = 1
+// ```
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Meal(pub Vec<JournalEntry>);
+
+impl Data for Meal {
+    type Key = str;
+    const DIR: &str = "meal";
+    const GUIDE: &str = journal::GUIDE;
+
+    fn path(key: &str) -> std::path::PathBuf {
+        [Self::DIR, key]
+            .iter()
+            .collect::<std::path::PathBuf>()
+            .with_extension("txt")
+    }
+
+    fn load(
+        r: impl std::io::BufRead,
+        load_food: impl FnMut(&str) -> Result<Option<Food>>,
+    ) -> Result<Self> {
+        Ok(Self(Journal::load(r, load_food)?.0))
+    }
+
+    fn save(&self, w: &mut impl std::io::Write) -> Result<()> {
+        journal::save_entries(&self.0, w)
+    }
+
+    fn check(data: &Database, path: &Path, text: &str) -> Vec<check::Problem> {
+        // Meals have the same format as journals.
+        check::journal(data, path, text)
+    }
+}
@@ -0,0 +1,242 @@
+use crate::Serving;
+
+// Merge changes into the text of an INI file, keeping its comments, blank lines and order.
+// Data::save writes a file from scratch, so saving it directly would discard anything
+// the user wrote by hand. Instead, the saved text is compared to the existing text,
+// section by section, and only the entries that differ are changed.
+
+// A line of an INI file. Entries are key = value, where the value may be empty.
+struct Line<'a> {
+    raw: &'a str,
+    entry: Option<(&'a str, &'a str)>,
+}
+
+// A section of an INI file, with the lines that follow its header.
+struct Section<'a> {
+    name: Option<&'a str>, // None for the general section
+    header: Option<&'a str>,
+    lines: Vec<Line<'a>>,
+}
+
+// Split INI text into sections, following the same rules as refs::keys.
+fn parse(text: &str) -> Vec<Section<'_>> {
+    let mut res = vec![Section {
+        name: None,
+        header: None,
+        lines: vec![],
+    }];
+    for raw in text.lines() {
+        let line = raw.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            res.push(Section {
+                name: Some(name.trim()),
+                header: Some(raw),
+                lines: vec![],
+            });
+            continue;
+        }
+        let entry = if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            None
+        } else {
+            Some(match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (line, ""),
+            })
+        };
+        if let Some(last) = res.last_mut() {
+            last.lines.push(Line { raw, entry });
+        }
+    }
+    res
+}
+
+// Whether two values are the same, so that "0.5c" isn't rewritten as "0.5 c".
+// Values are compared in parts separated by "|", as in journals. Parts that are
+// servings or numbers are compared by size and unit, and others must match exactly.
+fn same_value(a: &str, b: &str) -> bool {
+    let same_part = |a: &str, b: &str| {
+        let (a, b) = (a.trim(), b.trim());
+        a == b
+            || matches!(
+                (a.parse::<Serving>(), b.parse::<Serving>()),
+                (Ok(a), Ok(b)) if a.size == b.size && a.unit == b.unit
+            )
+    };
+    a.split('|').count() == b.split('|').count()
+        && a.split('|').zip(b.split('|')).all(|(a, b)| same_part(a, b))
+}
+
+// Rewrite the value of an entry, keeping the key as it was written.
+fn set_value(raw: &str, key: &str, value: &str) -> String {
+    match raw.find('=') {
+        Some(idx) => {
+            let space = if raw[idx + 1..].starts_with(' ') {
+                " "
+            } else {
+                ""
+            };
+            format!("{}{space}{value}", &raw[..=idx])
+        }
+        None => format!("{key} = {value}"),
+    }
+}
+
+// Return, for each entry of old, the index of the matching entry of new,
+// using the longest common subsequence so unchanged entries stay in place.
+fn matches(old: &[(&str, &str)], new: &[(&str, &str)]) -> Vec<Option<usize>> {
+    let same = |a: &(&str, &str), b: &(&str, &str)| a.0 == b.0 && same_value(a.1, b.1);
+    // lcs[i][j] is the length of the LCS of old[i..] and new[j..].
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if same(&old[i], &new[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut res = vec![None; old.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if same(&old[i], &new[j]) {
+            res[i] = Some(j);
+            (i, j) = (i + 1, j + 1);
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    res
+}
+
+// Merge the entries of a new section into the lines of an old one.
+fn merge_lines(old: &[Line], new: &[Line], out: &mut Vec<String>) {
+    let old_entries: Vec<_> = old.iter().filter_map(|l| l.entry).collect();
+    let new_lines: Vec<_> = new.iter().filter(|l| l.entry.is_some()).collect();
+    let new_entries: Vec<_> = new_lines.iter().filter_map(|l| l.entry).collect();
+    let matched = matches(&old_entries, &new_entries);
+
+    let start = out.len();
+    // Where entries that are new go: after the last entry, before any trailing comments.
+    let mut end = start;
+    let mut j = 0; // The next entry of new to output.
+    let mut entries = matched.iter();
+    for line in old {
+        let Some((key, _)) = line.entry else {
+            out.push(line.raw.into());
+            continue;
+        };
+        match entries.next().copied().flatten() {
+            Some(m) => {
+                // Add the new entries that come before this one.
+                out.extend(new_lines[j..m].iter().map(|l| l.raw.to_string()));
+                out.push(line.raw.into());
+                j = m + 1;
+            }
+            None => {
+                // If the next new entry has the same key, and doesn't match a later entry,
+                // the value changed. Otherwise the entry was removed.
+                let next_match = matched.iter().flatten().find(|&&m| m >= j);
+                match new_entries.get(j) {
+                    Some((k, value)) if *k == key && next_match.is_none_or(|&m| m > j) => {
+                        out.push(set_value(line.raw, key, value));
+                        j += 1;
+                    }
+                    _ => continue,
+                }
+            }
+        }
+        end = out.len();
+    }
+    let rest: Vec<_> = new_lines[j..].iter().map(|l| l.raw.to_string()).collect();
+    out.splice(end..end, rest);
+}
+
+// Merge new INI text into old, keeping comments, blank lines and the order of old.
+// Entries and sections that are only in new are added, and those only in old are removed.
+pub fn merge(old: &str, new: &str) -> String {
+    let old = parse(old);
+    let new = parse(new);
+    let mut out: Vec<String> = vec![];
+    for section in &old {
+        let Some(n) = new.iter().find(|s| s.name == section.name) else {
+            continue;
+        };
+        out.extend(section.header.map(str::to_string));
+        merge_lines(&section.lines, &n.lines, &mut out);
+    }
+    for section in new.iter().filter(|n| old.iter().all(|s| s.name != n.name)) {
+        if section.header.is_some() && out.last().is_some_and(|l| !l.trim().is_empty()) {
+            out.push("".into());
+        }
+        out.extend(section.header.map(str::to_string));
+        out.extend(section.lines.iter().map(|l| l.raw.to_string()));
+    }
+    while out.last().is_some_and(|l| l.trim().is_empty()) {
+        out.pop();
+    }
+    let mut res = out.join("\n");
+    res.push('\n');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Data, Food, Journal};
+    use pretty_assertions::assert_eq;
+
+    fn load<T: Data>(text: &str) -> T {
+        T::load(text.as_bytes(), |_| Ok(Some(Food::default()))).unwrap()
+    }
+
+    // Each directory under testdata/merge has old.txt and new.txt,
+    // and merged.txt with the expected result of merging them.
+    #[test]
+    fn test_merge_golden() {
+        let mut count = 0;
+        for entry in std::fs::read_dir("tests/testdata/merge").unwrap() {
+            let dir = entry.unwrap().path();
+            let read = |name| std::fs::read_to_string(dir.join(name)).unwrap();
+            let merged = merge(&read("old.txt"), &read("new.txt"));
+            assert_eq!(merged, read("merged.txt"), "{dir:?}");
+            // The merged text loads the same as the new text.
+            let name = dir.file_name().unwrap().to_str().unwrap();
+            if name.starts_with("food") {
+                assert_eq!(
+                    load::<Food>(&merged),
+                    load::<Food>(&read("new.txt")),
+                    "{dir:?}"
+                );
+            } else {
+                assert_eq!(
+                    load::<Journal>(&merged),
+                    load::<Journal>(&read("new.txt")),
+                    "{dir:?}"
+                );
+            }
+            // Merging again changes nothing.
+            assert_eq!(merge(&merged, &read("new.txt")), merged, "{dir:?}");
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_same_value() {
+        assert!(same_value("0.5c", "0.5 c"));
+        assert!(same_value("1.0", "1"));
+        assert!(same_value("", ""));
+        assert!(same_value("1.0c", "1c"));
+        assert!(same_value(
+            "0.5c | 08:15 | weighed dry",
+            "0.5 c|08:15|weighed dry"
+        ));
+        assert!(!same_value("1", "2"));
+        assert!(!same_value("Oat milk", "Oatmilk"));
+        assert!(!same_value("1 | | oat milk", "1 | | oatmilk"));
+        assert!(!same_value("1 c", "1 c | 08:15"));
+    }
+}
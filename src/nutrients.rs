@@ -1,6 +1,5 @@
 // The macronutrients of a food.
-#[derive(Clone, Copy, Debug, Default)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize)]
 pub struct Nutrients {
     pub carb: f32,
     pub fat: f32,
@@ -25,6 +24,62 @@ impl Nutrients {
     }
 }
 
+// The reference daily values (DV) used on FDA nutrition labels, for adults eating
+// 2000 kcal a day, by nutrient name. They can be overridden by goals, see Goals.
+pub const DAILY_VALUES: [(&str, f32); 4] = [
+    ("carb", 275.0),
+    ("fat", 78.0),
+    ("protein", 50.0),
+    ("kcal", 2000.0),
+];
+
+impl Nutrients {
+    // Get a nutrient by name, such as "carb".
+    pub fn get(&self, name: &str) -> Option<f32> {
+        match name {
+            "carb" => Some(self.carb),
+            "fat" => Some(self.fat),
+            "protein" => Some(self.protein),
+            "kcal" => Some(self.kcal),
+            _ => None,
+        }
+    }
+
+    // Get a mutable nutrient by name, such as "carb".
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut f32> {
+        match name {
+            "carb" => Some(&mut self.carb),
+            "fat" => Some(&mut self.fat),
+            "protein" => Some(&mut self.protein),
+            "kcal" => Some(&mut self.kcal),
+            _ => None,
+        }
+    }
+
+    // The reference daily values of each nutrient.
+    pub fn daily_values() -> Self {
+        let mut res = Self::default();
+        for (name, value) in DAILY_VALUES {
+            if let Some(n) = res.get_mut(name) {
+                *n = value;
+            }
+        }
+        res
+    }
+
+    // The percent of the given daily values (%DV) in these nutrients.
+    // A nutrient without a daily value is 0%.
+    pub fn percent_of(&self, dv: &Nutrients) -> Nutrients {
+        let percent = |n: f32, dv: f32| if dv > 0.0 { 100.0 * n / dv } else { 0.0 };
+        Nutrients {
+            carb: percent(self.carb, dv.carb),
+            fat: percent(self.fat, dv.fat),
+            protein: percent(self.protein, dv.protein),
+            kcal: percent(self.kcal, dv.kcal),
+        }
+    }
+}
+
 impl std::ops::Add<Nutrients> for Nutrients {
     type Output = Nutrients;
 
@@ -93,3 +148,26 @@ fn test_nutrient_kcal_computation() {
     assert_eq!(nut.protein, 3.1);
     assert_eq!(nut.kcal, 37.9);
 }
+
+#[test]
+fn test_nutrient_percent() {
+    let dv = Nutrients::daily_values();
+    assert_eq!(dv.get("kcal"), Some(2000.0));
+    assert_eq!(dv.get("fiber"), None);
+    let nut = Nutrients {
+        carb: 27.5,
+        fat: 0.0,
+        protein: 25.0,
+        kcal: 500.0,
+    };
+    assert_eq!(
+        nut.percent_of(&dv),
+        Nutrients {
+            carb: 10.0,
+            fat: 0.0,
+            protein: 50.0,
+            kcal: 25.0,
+        }
+    );
+    assert_eq!(nut.percent_of(&Nutrients::default()), Nutrients::default());
+}
@@ -0,0 +1,263 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::search::page_size;
+use crate::{http::Client, Details, Food, FoodSpec, Hit, Nutrients, Provider, Results};
+
+const OFF_URL: &str = "https://world.openfoodfacts.org/cgi/search.pl";
+
+// Search for a food on Open Food Facts
+// https://openfoodfacts.github.io/openfoodfacts-server/api/
+pub struct OpenFoodFacts<'a> {
+    pub page_size: usize,
+    pub url: &'a str,
+    pub client: &'a Client,
+}
+
+impl<'a> Default for OpenFoodFacts<'a> {
+    fn default() -> Self {
+        Self {
+            url: OFF_URL,
+            page_size: 50,
+            client: Client::shared(),
+        }
+    }
+}
+
+impl<'a> Provider for OpenFoodFacts<'a> {
+    fn search(&self, term: &str, page: usize) -> Result<Results> {
+        let page_size = page_size(self.page_size);
+        let req = self
+            .client
+            .get(self.url)
+            .query(&[("search_terms", term)])
+            .query(&[("search_simple", "1"), ("action", "process"), ("json", "1")])
+            .query(&[("page", page)])
+            .query(&[("page_size", page_size)])
+            .build()?;
+
+        let res: Page = serde_json::from_str(&self.client.fetch(req)?)?;
+        Ok(Results {
+            hits: res
+                .products
+                .iter()
+                .map(|p| Hit {
+                    food: p.into(),
+                    details: p.details(),
+                })
+                .collect(),
+            page,
+            total_pages: res.count.div_ceil(page_size),
+            total_hits: res.count,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Page {
+    #[serde(default)]
+    count: usize,
+    products: Vec<Product>,
+}
+
+// Some numeric fields are inconsistently encoded as either numbers or strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Number(f32),
+    String(String),
+}
+
+impl Number {
+    fn value(&self) -> Option<f32> {
+        match self {
+            Number::Number(n) => Some(*n),
+            Number::String(s) => s.trim().parse().ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Product {
+    code: Option<String>,             // "3017620422003"
+    product_name: Option<String>,     // "Nutella"
+    brands: Option<String>,           // "Ferrero"
+    ingredients_text: Option<String>, // "Sugar, palm oil, hazelnuts 13%, ..."
+    serving_size: Option<String>,     // "1 tbsp (15 g)"
+    serving_quantity: Option<Number>, // 15
+    #[serde(default)]
+    nutriments: HashMap<String, Number>, // {"fat_100g": 30.9, ...}
+}
+
+impl Product {
+    // Return a nutrient per 100g.
+    fn nutrient(&self, name: &str) -> Option<f32> {
+        self.nutriments
+            .get(&format!("{name}_100g"))
+            .and_then(Number::value)
+    }
+
+    fn nutrients(&self) -> Nutrients {
+        Nutrients {
+            carb: self.nutrient("carbohydrates").unwrap_or_default(),
+            fat: self.nutrient("fat").unwrap_or_default(),
+            protein: self.nutrient("proteins").unwrap_or_default(),
+            kcal: self
+                .nutrient("energy-kcal")
+                .or_else(|| self.nutrient("energy").map(|kj| kj / 4.184))
+                .unwrap_or_default(),
+        }
+        .maybe_compute_kcal()
+    }
+
+    fn details(&self) -> Details {
+        let mut nutrients: Vec<_> = self
+            .nutriments
+            .iter()
+            .filter_map(|(k, v)| {
+                let name = k.strip_suffix("_100g")?;
+                let unit = match self.nutriments.get(&format!("{name}_unit")) {
+                    Some(Number::String(unit)) => unit.clone(),
+                    _ => "".into(),
+                };
+                Some((name.to_string(), v.value()?, unit))
+            })
+            .collect();
+        nutrients.sort_by(|a, b| a.0.cmp(&b.0));
+        Details {
+            brand: self.brands.clone(),
+            ingredients: self.ingredients_text.clone(),
+            nutrients,
+        }
+    }
+
+    // The size of one serving in grams, if known.
+    fn serving_grams(&self) -> Option<f32> {
+        self.serving_quantity
+            .as_ref()
+            .and_then(Number::value)
+            .filter(|q| *q > 0.0)
+    }
+
+    // A serving described in household units, e.g. "1/2 cup (40 g)" => ("cup", 0.5).
+    fn household_serving(&self) -> Option<(String, f32)> {
+        let text = self.serving_size.as_ref()?.split('(').next()?.trim();
+        let (amount, unit) = text.split_once(char::is_whitespace)?;
+        let amount = match amount.split_once('/') {
+            Some((n, d)) => n.parse::<f32>().ok()? / d.parse::<f32>().ok()?,
+            None => amount.parse().ok()?,
+        };
+        let unit = unit.trim();
+        if unit == "g" {
+            // Already covered by serving_quantity.
+            return None;
+        }
+        Some((unit.into(), amount))
+    }
+}
+
+// Nutriments are given per 100g.
+// If the product has a serving size, the food is scaled so one serving is one
+// product serving, otherwise one serving is 100g.
+impl From<&Product> for Food {
+    fn from(value: &Product) -> Self {
+        let per_100g = value.nutrients();
+        let (nutrients, servings) = match value.serving_grams() {
+            Some(g) => {
+                let mut servings = vec![("g".to_string(), g)];
+                servings.extend(value.household_serving());
+                (per_100g * (g / 100.0), servings)
+            }
+            None => (per_100g, vec![("g".to_string(), 100.0)]),
+        };
+        Food {
+            name: value.product_name.clone().unwrap_or_default(),
+            spec: FoodSpec::Nutrients(nutrients),
+            servings,
+            barcode: value.code.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httptest::{matchers::*, responders::*, Expectation, Server};
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn test_search_off() {
+        let _ = env_logger::try_init();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/test"),
+                request::query(url_decoded(contains(("search_terms", "oats")))),
+                request::query(url_decoded(contains(("page_size", "2")))),
+                request::query(url_decoded(contains(("page", "1"))))
+            ])
+            .respond_with(
+                status_code(200)
+                    .body(fs::read_to_string("tests/testdata/search/off/page1.json").unwrap()),
+            ),
+        );
+        let url = server.url("/test");
+
+        let search = OpenFoodFacts {
+            page_size: 2,
+            url: &url.to_string(),
+            ..Default::default()
+        };
+        let actual = search.search("oats", 1).unwrap();
+        assert_eq!(actual.page, 1);
+        assert_eq!(actual.total_pages, 1457);
+        assert_eq!(actual.total_hits, 2914);
+        assert_eq!(
+            actual.hits[0].details,
+            Details {
+                brand: Some("Bob's Red Mill".into()),
+                ingredients: Some("Whole grain rolled oats.".into()),
+                nutrients: vec![
+                    ("carbohydrates".into(), 60.0, "g".into()),
+                    ("energy".into(), 1569.0, "".into()),
+                    ("energy-kcal".into(), 375.0, "".into()),
+                    ("fat".into(), 7.0, "".into()),
+                    ("proteins".into(), 12.5, "".into()),
+                    ("sodium".into(), 0.0, "".into()),
+                ],
+            }
+        );
+        assert_eq!(
+            actual.hits.into_iter().map(|h| h.food).collect::<Vec<_>>(),
+            vec![
+                Food {
+                    name: "Rolled Oats".into(),
+                    spec: FoodSpec::Nutrients(Nutrients {
+                        carb: 24.0,
+                        fat: 2.8,
+                        protein: 5.0,
+                        kcal: 150.0,
+                    }),
+                    servings: vec![("g".into(), 40.0), ("cup".into(), 0.5)],
+                    barcode: Some("0039978003096".into()),
+                    ..Default::default()
+                },
+                Food {
+                    name: "Haferflocken".into(),
+                    spec: FoodSpec::Nutrients(Nutrients {
+                        carb: 58.7,
+                        fat: 7.0,
+                        protein: 13.5,
+                        kcal: 372.0,
+                    }),
+                    servings: vec![("g".into(), 100.0)],
+                    barcode: Some("4061458009428".into()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// Helpers for finding and rewriting the food keys that data files refer to.
+// These work on the raw text, rather than going through Ini, so that
+// comments and formatting are preserved when a file is rewritten.
+
+// A reference to a food key on a line of a data file.
+#[derive(Debug, PartialEq)]
+pub struct Ref<'a> {
+    pub line: usize, // starts at 1
+    pub key: &'a str,
+    pub value: &'a str, // empty if the line has no "="
+}
+
+// Return the keys in a section of an INI file, with their line numbers.
+// A section of None is the general section, before any [section] header.
+pub fn keys<'a>(text: &'a str, section: Option<&'a str>) -> impl Iterator<Item = Ref<'a>> {
+    let mut current = None;
+    text.lines().enumerate().filter_map(move |(i, line)| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            return None;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name.trim());
+            return None;
+        }
+        if current != section {
+            return None;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (line, ""),
+        };
+        Some(Ref {
+            line: i + 1,
+            key,
+            value,
+        })
+    })
+}
+
+// Return the names of all sections of an INI file.
+pub fn sections(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().filter_map(|line| {
+        let line = line.trim();
+        Some(line.strip_prefix('[')?.strip_suffix(']')?.trim())
+    })
+}
+
+// Return the food keys a journal refers to in the food database.
+// Foods defined inline, in a section of the journal, are excluded.
+pub fn journal_keys(text: &str) -> impl Iterator<Item = Ref<'_>> {
+    let inline: Vec<_> = sections(text).collect();
+    keys(text, None).filter(move |r| !inline.contains(&r.key))
+}
+
+// Return the food keys a recipe refers to in the food database.
+pub fn recipe_keys(text: &str) -> impl Iterator<Item = Ref<'_>> {
+    keys(text, Some("ingredients"))
+}
+
+// Replace every key old with new in a section of an INI file.
+// Returns None if nothing was replaced.
+pub fn rename_key(text: &str, section: Option<&str>, old: &str, new: &str) -> Option<String> {
+    let lines: Vec<_> = keys(text, section)
+        .filter(|r| r.key == old)
+        .map(|r| r.line)
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let mut res = String::with_capacity(text.len());
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if lines.contains(&(i + 1)) {
+            let start = line.find(old).unwrap_or_default();
+            res.push_str(&line[..start]);
+            res.push_str(new);
+            res.push_str(&line[start + old.len()..]);
+        } else {
+            res.push_str(line);
+        }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const RECIPE: &str = "\
+name = Oatmeal
+; plain oats
+[ingredients]
+oats = 0.5c
+  banana=1
+berries
+
+[servings]
+oats = 1
+";
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(
+            keys(RECIPE, Some("ingredients")).collect::<Vec<_>>(),
+            vec![
+                Ref {
+                    line: 4,
+                    key: "oats",
+                    value: "0.5c",
+                },
+                Ref {
+                    line: 5,
+                    key: "banana",
+                    value: "1",
+                },
+                Ref {
+                    line: 6,
+                    key: "berries",
+                    value: "",
+                },
+            ]
+        );
+        assert_eq!(
+            keys(RECIPE, None).collect::<Vec<_>>(),
+            vec![Ref {
+                line: 1,
+                key: "name",
+                value: "Oatmeal",
+            }]
+        );
+    }
+
+    #[test]
+    fn test_journal_keys() {
+        let journal = "oats = 1\nbanana = 2\n\n[oats]\nname = Oats\n";
+        assert_eq!(
+            journal_keys(journal).collect::<Vec<_>>(),
+            vec![Ref {
+                line: 2,
+                key: "banana",
+                value: "2",
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rename_key() {
+        assert_eq!(
+            rename_key(RECIPE, Some("ingredients"), "oats", "rolled_oats").unwrap(),
+            RECIPE.replacen("oats = 0.5c", "rolled_oats = 0.5c", 1)
+        );
+        assert_eq!(
+            rename_key(RECIPE, Some("ingredients"), "banana", "plantain").unwrap(),
+            RECIPE.replace("  banana=1", "  plantain=1")
+        );
+        assert_eq!(
+            rename_key(RECIPE, Some("ingredients"), "berries", "blueberries").unwrap(),
+            RECIPE.replace("\nberries\n", "\nblueberries\n")
+        );
+        assert_eq!(rename_key(RECIPE, Some("ingredients"), "nope", "x"), None);
+    }
+}
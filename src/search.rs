@@ -1,10 +1,41 @@
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{FoodSpec, Nutrients};
+use crate::{http::Client, Food, FoodSpec, Nutrients};
 
 const FDC_URL: &str = "https://api.nal.usda.gov/fdc/v1/foods/search";
 
+// A source of foods that can be searched by name.
+pub trait Provider {
+    // Return one page of results for a search term.
+    // Pages start at 1. Pages past the last one have no hits.
+    fn search(&self, term: &str, page: usize) -> Result<Results>;
+}
+
+// One page of search results.
+pub struct Results {
+    pub hits: Vec<Hit>,
+    pub page: usize, // starts at 1
+    pub total_pages: usize,
+    pub total_hits: usize,
+}
+
+// A single search result.
+pub struct Hit {
+    pub food: Food,
+    pub details: Details,
+}
+
+// Extra information about a search result that is not saved with the food.
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Details {
+    pub brand: Option<String>,
+    pub ingredients: Option<String>,
+    // Every nutrient reported for 100g of the food, as (name, amount, unit).
+    pub nutrients: Vec<(String, f32, String)>,
+}
+
 // Search for a food on Food Data Central
 // https://fdc.nal.usda.gov/api-guide.html
 pub struct Search<'a> {
@@ -12,6 +43,7 @@ pub struct Search<'a> {
     pub page_size: usize,
     pub url: &'a str,
     pub page: usize, // starts at 1
+    pub client: &'a Client,
 }
 
 impl<'a> Default for Search<'a> {
@@ -21,46 +53,76 @@ impl<'a> Default for Search<'a> {
             url: FDC_URL,
             page_size: 50,
             page: 1,
+            client: Client::shared(),
         }
     }
 }
 
+// The number of results to request for each page, given the size a provider
+// was created with. A page must have room for at least one result.
+pub(crate) fn page_size(size: usize) -> usize {
+    size.max(1)
+}
+
 impl<'a> Search<'a> {
     // Return the next page of results.
     pub fn next_page(&mut self) -> Result<Page> {
-        let client = reqwest::blocking::Client::new();
+        let res = self.fetch(self.term, self.page)?;
+        self.page += 1;
+        Ok(res)
+    }
 
-        let req = client
+    fn fetch(&self, term: &str, page: usize) -> Result<Page> {
+        let req = self
+            .client
             .get(self.url)
             .header("X-Api-Key", "DEMO_KEY")
-            .query(&[("query", self.term)])
-            .query(&[("pageNumber", self.page)])
-            .query(&[("pageSize", self.page_size)])
+            .query(&[("query", term)])
+            .query(&[("pageNumber", page)])
+            .query(&[("pageSize", page_size(self.page_size))])
             .build()?;
 
-        log::debug!("Sending request: {req:?}");
+        Ok(serde_json::from_str(&self.client.fetch(req)?)?)
+    }
+}
 
-        let res: Page = client.execute(req)?.error_for_status()?.json()?;
-        self.page += 1;
-        Ok(res)
+impl<'a> Provider for Search<'a> {
+    fn search(&self, term: &str, page: usize) -> Result<Results> {
+        let res = self.fetch(term, page)?;
+        Ok(Results {
+            hits: res.hits().collect(),
+            page,
+            total_pages: res.total_pages,
+            total_hits: res.total_hits,
+        })
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct SearchNutrient {
-    nutrient_id: u32,
-    value: f32,
+pub(crate) struct SearchNutrient {
+    pub nutrient_id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nutrient_name: Option<String>, // "Protein"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_name: Option<String>, // "G"
+    pub value: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct SearchFood {
-    description: Option<String>,
-    serving_size: Option<f32>,                   // 144.0
-    serving_size_unit: Option<String>,           // "g"
-    household_serving_full_text: Option<String>, // "1 cup"
-    food_nutrients: Option<Vec<SearchNutrient>>,
+pub(crate) struct SearchFood {
+    pub fdc_id: Option<u32>,
+    pub description: Option<String>,
+    pub serving_size: Option<f32>,                   // 144.0
+    pub serving_size_unit: Option<String>,           // "g"
+    pub household_serving_full_text: Option<String>, // "1 cup"
+    pub gtin_upc: Option<String>,                    // "709481000201"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brand_owner: Option<String>, // "Kasia's Deli Inc."
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingredients: Option<String>, // "FRESH POTATOES, LIQUID WHOLE EGGS, ..."
+    pub food_nutrients: Option<Vec<SearchNutrient>>,
 }
 
 impl SearchFood {
@@ -121,14 +183,37 @@ impl SearchFood {
         }
         res
     }
+
+    fn details(&self) -> Details {
+        Details {
+            brand: self.brand_owner.as_ref().map(|b| b.trim().to_string()),
+            ingredients: self.ingredients.clone(),
+            nutrients: self
+                .food_nutrients
+                .iter()
+                .flatten()
+                .map(|n| {
+                    (
+                        n.nutrient_name
+                            .clone()
+                            .unwrap_or_else(|| format!("Nutrient {}", n.nutrient_id)),
+                        n.value,
+                        n.unit_name.clone().unwrap_or_default().to_lowercase(),
+                    )
+                })
+                .collect(),
+        }
+    }
 }
 
-impl From<&SearchFood> for crate::Food {
+impl From<&SearchFood> for Food {
     fn from(value: &SearchFood) -> Self {
-        crate::Food {
+        Food {
             spec: FoodSpec::Nutrients(value.nutrients()),
             servings: value.servings(),
             name: value.description.clone().unwrap_or_default(),
+            barcode: value.gtin_upc.clone(),
+            ..Default::default()
         }
     }
 }
@@ -136,19 +221,39 @@ impl From<&SearchFood> for crate::Food {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Page {
-    foods: Vec<SearchFood>,
+    pub(crate) foods: Vec<SearchFood>,
+    #[serde(default)]
+    total_hits: usize,
+    #[serde(default)]
+    total_pages: usize,
+}
+
+impl From<Vec<SearchFood>> for Page {
+    fn from(foods: Vec<SearchFood>) -> Self {
+        Page {
+            total_hits: foods.len(),
+            total_pages: 1,
+            foods,
+        }
+    }
 }
 
 impl Page {
-    pub fn iter(&self) -> impl Iterator<Item = crate::Food> + '_ {
-        self.foods.iter().map(|x| crate::Food::from(x))
+    pub fn iter(&self) -> impl Iterator<Item = Food> + '_ {
+        self.foods.iter().map(Food::from)
+    }
+
+    pub(crate) fn hits(&self) -> impl Iterator<Item = Hit> + '_ {
+        self.foods.iter().map(|f| Hit {
+            food: f.into(),
+            details: f.details(),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Food;
     use httptest::{matchers::*, responders::*, Expectation, Server};
     use pretty_assertions::assert_eq;
     use std::fs;
@@ -177,6 +282,7 @@ mod tests {
             page_size: 2,
             url: &url.to_string(),
             page: 1,
+            ..Default::default()
         };
         let actual = search.next_page().unwrap();
         assert_eq!(
@@ -191,6 +297,7 @@ mod tests {
                         kcal: 353.0
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
                 Food {
                     name: "Potatoes, gold, without skin, raw".into(),
@@ -201,6 +308,7 @@ mod tests {
                         kcal: 71.6,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
             ]
         );
@@ -218,6 +326,7 @@ mod tests {
             page_size: 2,
             url: &url.to_string(),
             page: 1,
+            ..Default::default()
         };
         let actual = search.next_page().unwrap();
         assert_eq!(
@@ -232,6 +341,7 @@ mod tests {
                         kcal: 171.0,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
                 Food {
                     name: "Potato pancake".into(),
@@ -242,6 +352,7 @@ mod tests {
                         kcal: 196.0,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
             ]
         );
@@ -259,6 +370,7 @@ mod tests {
             page_size: 2,
             url: &url.to_string(),
             page: 1,
+            ..Default::default()
         };
         let actual = search.next_page().unwrap();
         assert_eq!(
@@ -273,6 +385,7 @@ mod tests {
                         kcal: 266.0,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
                 Food {
                     name: "Potato flour".into(),
@@ -283,6 +396,7 @@ mod tests {
                         kcal: 357.0,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
             ]
         );
@@ -300,6 +414,7 @@ mod tests {
             page_size: 2,
             url: &url.to_string(),
             page: 1,
+            ..Default::default()
         };
         let actual = search.next_page().unwrap();
         assert_eq!(
@@ -314,6 +429,8 @@ mod tests {
                         kcal: 158.0,
                     }),
                     servings: vec![("GRM".into(), 57.0), ("PANCAKE".into(), 1.0)],
+                    barcode: Some("709481000201".into()),
+                    ..Default::default()
                 },
                 Food {
                     name: "GNOCCHI WITH POTATO, POTATO".into(),
@@ -324,11 +441,49 @@ mod tests {
                         kcal: 136.0,
                     }),
                     servings: vec![("g".into(), 140.0), ("cup".into(), 1.0)],
+                    barcode: Some("07789048538".into()),
+                    ..Default::default()
                 },
             ]
         );
     }
 
+    #[test]
+    fn test_search_provider() {
+        let _ = env_logger::try_init();
+        let server = Server::run();
+        server.expect(expect_page("branded", 1));
+        let url = server.url("/test");
+
+        let search = Search {
+            page_size: 2,
+            url: &url.to_string(),
+            ..Default::default()
+        };
+        let actual = search.search("potato", 1).unwrap();
+        assert_eq!(actual.page, 1);
+        assert_eq!(actual.total_pages, 17322);
+        assert_eq!(actual.total_hits, 34644);
+        assert_eq!(actual.hits.len(), 2);
+
+        let details = &actual.hits[0].details;
+        assert_eq!(details.brand.as_deref(), Some("Kasia's Deli Inc."));
+        assert!(details
+            .ingredients
+            .as_deref()
+            .unwrap()
+            .starts_with("FRESH POTATOES, LIQUID WHOLE EGGS"));
+        assert_eq!(
+            details.nutrients[..4],
+            [
+                ("Protein".into(), 3.51, "g".into()),
+                ("Total lipid (fat)".into(), 7.02, "g".into()),
+                ("Carbohydrate, by difference".into(), 26.3, "g".into()),
+                ("Energy".into(), 158.0, "kcal".into()),
+            ]
+        );
+    }
+
     #[test]
     fn test_search_paged() {
         let _ = env_logger::try_init();
@@ -343,6 +498,7 @@ mod tests {
             page_size: 2,
             url: &url.to_string(),
             page: 1,
+            ..Default::default()
         };
 
         let actual = search.next_page().unwrap();
@@ -358,6 +514,7 @@ mod tests {
                         kcal: 353.0
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
                 Food {
                     name: "Potatoes, gold, without skin, raw".into(),
@@ -368,6 +525,7 @@ mod tests {
                         kcal: 71.6,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
             ]
         );
@@ -385,6 +543,7 @@ mod tests {
                         kcal: 73.4,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
                 Food {
                     name: "Potatoes, russet, without skin, raw".into(),
@@ -395,6 +554,7 @@ mod tests {
                         kcal: 81.0,
                     }),
                     servings: vec![("g".into(), 100.0)],
+                    ..Default::default()
                 },
             ]
         );
@@ -411,6 +571,7 @@ mod tests {
                     kcal: 77.4,
                 }),
                 servings: vec![("g".into(), 100.0)],
+                ..Default::default()
             },]
         );
     }
@@ -41,6 +41,31 @@ impl std::fmt::Display for Serving {
     }
 }
 
+// Millilitres in one of each unit of volume.
+const VOLUMES: &[(&str, f32)] = &[
+    ("ml", 1.0),
+    ("cl", 10.0),
+    ("dl", 100.0),
+    ("l", 1000.0),
+    ("tsp", 5.0),
+    ("tbsp", 15.0),
+    ("floz", 29.57),
+    ("fl oz", 29.57),
+    ("cup", 240.0),
+    ("cups", 240.0),
+];
+
+impl Serving {
+    // The volume of this serving in ml, if its unit is a unit of volume.
+    pub fn ml(&self) -> Option<f32> {
+        let unit = self.unit.as_ref()?.to_lowercase();
+        VOLUMES
+            .iter()
+            .find(|(u, _)| *u == unit)
+            .map(|(_, ml)| self.size * ml)
+    }
+}
+
 // A serving can be parsed from a strings of form:
 // "1.5", "1.5cups", "1.5 cups"
 impl FromStr for Serving {
@@ -48,7 +73,7 @@ impl FromStr for Serving {
 
     fn from_str(s: &str) -> std::prelude::v1::Result<Self, Self::Err> {
         let s = s.trim();
-        let (size, unit) = match s.find(|c: char| c != '.' && !c.is_digit(10)) {
+        let (size, unit) = match s.find(|c: char| c != '.' && !c.is_ascii_digit()) {
             Some(idx) => {
                 let (size, unit) = s.split_at(idx);
                 (size.trim(), Some(unit.trim()))
@@ -78,3 +103,13 @@ fn test_parse_serving() {
     assert_eq!(parse("25g dry").unwrap(), serv(25.0, Some("g dry")));
     assert!(parse("cup 1.5").is_err());
 }
+
+#[test]
+fn test_serving_ml() {
+    let ml = |s: &str| s.parse::<Serving>().unwrap().ml();
+    assert_eq!(ml("500ml"), Some(500.0));
+    assert_eq!(ml("1.5 L"), Some(1500.0));
+    assert_eq!(ml("2 cups"), Some(480.0));
+    assert_eq!(ml("100g"), None);
+    assert_eq!(ml("2"), None);
+}
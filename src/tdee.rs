@@ -0,0 +1,138 @@
+use crate::trend;
+use chrono::NaiveDate;
+
+// The energy in a kilogram of body weight, in kcal.
+// Weight is mostly gained or lost as fat, with some water and lean tissue.
+pub const KCAL_PER_KG: f32 = 7700.0;
+
+// An estimate of total daily energy expenditure (TDEE), the kcal burned per day,
+// from what was eaten and how weight changed over a period.
+#[derive(Debug, PartialEq)]
+pub struct Tdee {
+    // The estimated kcal burned per day.
+    pub kcal: f32,
+    // The average kcal eaten per day, on days with a journal.
+    pub intake: f32,
+    // The change in the weight trend per day, in kg.
+    pub change: f32,
+    // The number of days with a journal that the estimate is based on.
+    pub days: usize,
+}
+
+impl Tdee {
+    // Estimate TDEE over the days from start to end, inclusive.
+    // intake has the kcal eaten on each day with a journal, and weights the weight in kg
+    // on each day weighed, including the TREND_DAYS before start so the trend is smoothed.
+    // The kcal that weight changed by is subtracted from intake: eating more than
+    // you burn is stored as weight, and eating less uses some up.
+    // Returns None without enough data: a journal, and a trend on both start and end.
+    pub fn estimate(
+        intake: &[(NaiveDate, f32)],
+        weights: &[(NaiveDate, f32)],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Option<Self> {
+        let span = (end - start).num_days();
+        if span <= 0 {
+            return None;
+        }
+        let eaten: Vec<_> = intake
+            .iter()
+            .filter(|(d, _)| (start..=end).contains(d))
+            .map(|(_, kcal)| kcal)
+            .collect();
+        if eaten.is_empty() {
+            return None;
+        }
+        let intake = eaten.iter().copied().sum::<f32>() / eaten.len() as f32;
+        let change = (trend(weights, end)? - trend(weights, start)?) / span as f32;
+        Some(Self {
+            kcal: intake - change * KCAL_PER_KG,
+            intake,
+            change,
+            days: eaten.len(),
+        })
+    }
+
+    // The kcal to eat per day to change weight by the given kg per week,
+    // which is negative to lose weight.
+    pub fn goal(&self, kg_per_week: f32) -> f32 {
+        self.kcal + kg_per_week * KCAL_PER_KG / 7.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TREND_DAYS;
+    use pretty_assertions::assert_eq;
+
+    fn day(n: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 7, 1).unwrap() + chrono::Duration::days(n)
+    }
+
+    type Days = Vec<(NaiveDate, f32)>;
+
+    // Synthetic history: eating kcal every day, while weight changes by change per day.
+    fn history(days: i64, kcal: f32, change: f32) -> (Days, Days) {
+        let intake = (0..days).map(|n| (day(n), kcal)).collect();
+        let weights = (0..days)
+            .map(|n| (day(n), 80.0 + change * n as f32))
+            .collect();
+        (intake, weights)
+    }
+
+    #[test]
+    fn test_estimate_stable() {
+        let (intake, weights) = history(28, 2500.0, 0.0);
+        let tdee = Tdee::estimate(&intake, &weights, day(TREND_DAYS), day(27)).unwrap();
+        assert_eq!(
+            tdee,
+            Tdee {
+                kcal: 2500.0,
+                intake: 2500.0,
+                change: 0.0,
+                days: 21
+            }
+        );
+        assert_eq!(tdee.goal(0.0), 2500.0);
+        assert_eq!(tdee.goal(-0.5), 1950.0);
+    }
+
+    #[test]
+    fn test_estimate_losing() {
+        // Losing 0.1 kg a day on 2000 kcal means burning 770 kcal more than that.
+        let (intake, weights) = history(35, 2000.0, -0.1);
+        let tdee = Tdee::estimate(&intake, &weights, day(TREND_DAYS), day(34)).unwrap();
+        assert!((tdee.change + 0.1).abs() < 1e-4, "{tdee:?}");
+        assert!((tdee.kcal - 2770.0).abs() < 1.0, "{tdee:?}");
+        assert!((tdee.goal(-0.7) - 2000.0).abs() < 1.0, "{tdee:?}");
+    }
+
+    #[test]
+    fn test_estimate_noisy() {
+        // Daily weights swing by a kilo, but the trend averages it out.
+        let (intake, mut weights) = history(42, 2200.0, 0.0);
+        for (i, (_, w)) in weights.iter_mut().enumerate() {
+            *w += if i % 2 == 0 { 0.5 } else { -0.5 };
+        }
+        // Start and end on days with the same number of high and low weights behind them.
+        let tdee = Tdee::estimate(&intake, &weights, day(13), day(41)).unwrap();
+        assert!((tdee.kcal - 2200.0).abs() < 1.0, "{tdee:?}");
+    }
+
+    #[test]
+    fn test_estimate_missing() {
+        let (intake, weights) = history(28, 2500.0, 0.0);
+        // No journals.
+        assert_eq!(Tdee::estimate(&[], &weights, day(7), day(27)), None);
+        // No weights near the start.
+        assert_eq!(Tdee::estimate(&intake, &weights, day(-30), day(27)), None);
+        // No days to compare.
+        assert_eq!(Tdee::estimate(&intake, &weights, day(27), day(27)), None);
+        // Days without journals aren't counted as eating nothing.
+        let some: Vec<_> = intake.into_iter().step_by(2).collect();
+        let tdee = Tdee::estimate(&some, &weights, day(7), day(27)).unwrap();
+        assert_eq!((tdee.kcal, tdee.days), (2500.0, 10));
+    }
+}
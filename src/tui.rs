@@ -0,0 +1,481 @@
+use anyhow::{Context, Result};
+use chrono::{Days, NaiveDate};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Cell, Clear, List, ListState, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+
+use crate::{fuzzy_score, Database, Journal, JournalEntry, Nutrients, Serving};
+
+// A full-screen view of one day's journal.
+// Foods are added by fuzzy searching the database and entering a serving.
+pub struct App<'a> {
+    data: &'a Database,
+    date: NaiveDate,
+    journal: Journal,
+    // (key, name) of every food in the database.
+    foods: Vec<(String, String)>,
+    mode: Mode,
+    selected: TableState,
+    // The last error or notice, shown at the bottom of the screen.
+    status: String,
+    quit: bool,
+}
+
+enum Mode {
+    Journal,
+    Search { query: String, selected: ListState },
+    Serving { key: String, input: String },
+}
+
+// Run the TUI until the user quits.
+pub fn run(data: &Database, date: NaiveDate) -> Result<()> {
+    let mut app = App::new(data, date)?;
+    let mut terminal = ratatui::init();
+    let res = app.run(&mut terminal);
+    ratatui::restore();
+    res
+}
+
+impl<'a> App<'a> {
+    pub fn new(data: &'a Database, date: NaiveDate) -> Result<App<'a>> {
+        let mut foods = vec![];
+        for key in data.list_food()? {
+            let key = key?;
+            match data.load_food(&key) {
+                Ok(Some(food)) => foods.push((key, food.name)),
+                Ok(None) => log::error!("Food '{key}' not found"),
+                Err(err) => log::error!("Failed to load food '{key}': {err:?}"),
+            }
+        }
+        foods.sort();
+        let mut app = App {
+            data,
+            date,
+            journal: Journal::default(),
+            foods,
+            mode: Mode::Journal,
+            selected: TableState::default(),
+            status: String::new(),
+            quit: false,
+        };
+        app.load()?;
+        Ok(app)
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|f| self.draw(f))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    // Load the journal for the current date.
+    fn load(&mut self) -> Result<()> {
+        self.journal = self.data.load_journal(&self.date)?.unwrap_or_default();
+        self.selected
+            .select((!self.journal.0.is_empty()).then_some(0));
+        Ok(())
+    }
+
+    fn goto(&mut self, date: Option<NaiveDate>) -> Result<()> {
+        self.date = date.context("Date out of range")?;
+        self.load()
+    }
+
+    // Handle a key press. Errors are shown in the status line.
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        self.status.clear();
+        if let Err(err) = self.try_handle_key(key) {
+            self.status = format!("{err:#}");
+        }
+    }
+
+    fn try_handle_key(&mut self, event: KeyEvent) -> Result<()> {
+        if event.modifiers.contains(KeyModifiers::CONTROL) && event.code == KeyCode::Char('c') {
+            self.quit = true;
+            return Ok(());
+        }
+        match &mut self.mode {
+            Mode::Journal => match event.code {
+                KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+                KeyCode::Left | KeyCode::Char('h') => {
+                    self.goto(self.date.checked_sub_days(Days::new(1)))?
+                }
+                KeyCode::Right | KeyCode::Char('l') => {
+                    self.goto(self.date.checked_add_days(Days::new(1)))?
+                }
+                KeyCode::Char('t') => self.goto(Some(chrono::Local::now().date_naive()))?,
+                KeyCode::Up | KeyCode::Char('k') => self.selected.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => self.selected.select_next(),
+                KeyCode::Char('d') | KeyCode::Delete => self.remove()?,
+                KeyCode::Char('a') | KeyCode::Char('/') => {
+                    self.mode = Mode::Search {
+                        query: String::new(),
+                        selected: ListState::default().with_selected(Some(0)),
+                    }
+                }
+                _ => {}
+            },
+            Mode::Search { query, selected } => match event.code {
+                KeyCode::Esc => self.mode = Mode::Journal,
+                KeyCode::Up => selected.select_previous(),
+                KeyCode::Down => selected.select_next(),
+                KeyCode::Backspace => {
+                    query.pop();
+                    selected.select(Some(0));
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    selected.select(Some(0));
+                }
+                KeyCode::Enter => {
+                    let idx = selected.selected().unwrap_or_default();
+                    let Some((key, _)) = matches(&self.foods, query).get(idx).copied() else {
+                        anyhow::bail!("No food matching '{query}'");
+                    };
+                    self.mode = Mode::Serving {
+                        key: key.clone(),
+                        input: String::new(),
+                    };
+                }
+                _ => {}
+            },
+            Mode::Serving { key, input } => match event.code {
+                KeyCode::Esc => self.mode = Mode::Journal,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Enter => {
+                    let serving: Serving = match input.trim() {
+                        "" => Serving::default(),
+                        s => s.parse()?,
+                    };
+                    let key = key.clone();
+                    self.add(key, serving)?;
+                    self.mode = Mode::Journal;
+                }
+                _ => {}
+            },
+        }
+        Ok(())
+    }
+
+    // Add a serving of a food to the journal and save it.
+    fn add(&mut self, key: String, serving: Serving) -> Result<()> {
+        let food = self
+            .data
+            .load_food(&key)?
+            .with_context(|| format!("No food with key {key:?}"))?;
+        food.serve(&serving)
+            .with_context(|| format!("Invalid serving: {serving}"))?;
+        log::debug!("Adding food={key} serving={serving} to {:?}", self.date);
+        let message = format!("eat {key} {serving} on {}", self.date);
+        let entry = JournalEntry {
+            key,
+            serving,
+            food,
+            ..Default::default()
+        };
+        // Only foods eaten today were eaten now.
+        let entry = if self.date == chrono::Local::now().date_naive() {
+            entry.eaten_now()
+        } else {
+            entry
+        };
+        self.journal.0.push(entry);
+        let res = self.data.transaction(&message, || {
+            self.data.save_journal(&self.date, &self.journal)
+        });
+        if let Err(err) = res {
+            // Show the journal as it is on disk.
+            self.journal.0.pop();
+            return Err(err);
+        }
+        self.selected.select(Some(self.journal.0.len() - 1));
+        Ok(())
+    }
+
+    // Remove the selected entry from the journal and save it.
+    fn remove(&mut self) -> Result<()> {
+        let Some(idx) = self.selected.selected() else {
+            return Ok(());
+        };
+        if idx >= self.journal.0.len() {
+            return Ok(());
+        }
+        let entry = self.journal.0.remove(idx);
+        log::debug!("Removing {} from {:?}", entry.key, self.date);
+        let message = format!("uneat {} {} on {}", entry.key, entry.serving, self.date);
+        let res = self.data.transaction(&message, || {
+            self.data.save_journal(&self.date, &self.journal)
+        });
+        if let Err(err) = res {
+            // Show the journal as it is on disk.
+            self.journal.0.insert(idx, entry);
+            return Err(err);
+        }
+        if self.journal.0.is_empty() {
+            self.selected.select(None);
+        } else if idx >= self.journal.0.len() {
+            self.selected.select(Some(idx - 1));
+        }
+        Ok(())
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Line::from(self.date.format("%A %Y-%m-%d").to_string())
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+            header,
+        );
+        self.draw_journal(frame, body);
+
+        let help = match self.mode {
+            Mode::Journal => "(a)dd (d)elete ←/→ day (t)oday (q)uit",
+            Mode::Search { .. } => "type to search, ↑/↓ select, enter pick, esc cancel",
+            Mode::Serving { .. } => "serving (e.g. 0.5 cups), enter add, esc cancel",
+        };
+        let footer_text = if self.status.is_empty() {
+            help
+        } else {
+            &self.status
+        };
+        frame.render_widget(Line::from(footer_text), footer);
+
+        match &mut self.mode {
+            Mode::Journal => {}
+            Mode::Search { query, selected } => {
+                draw_search(frame, body, &self.foods, query, selected)
+            }
+            Mode::Serving { key, input } => {
+                let area = body.centered(Constraint::Percentage(60), Constraint::Length(3));
+                frame.render_widget(Clear, area);
+                frame.render_widget(
+                    Paragraph::new(format!("{input}_"))
+                        .block(Block::bordered().title(format!("Serving of {key}"))),
+                    area,
+                );
+            }
+        }
+    }
+
+    // Draw the journal entries and their totals.
+    // Nutrients are computed the same way as `nosh journal show`.
+    fn draw_journal(&mut self, frame: &mut Frame, area: Rect) {
+        let fmt = |n: Nutrients| {
+            [
+                format!("{:.1}", n.carb),
+                format!("{:.1}", n.fat),
+                format!("{:.1}", n.protein),
+                format!("{:.0}", n.kcal),
+            ]
+        };
+        let rows = self.journal.0.iter().map(|entry| {
+            let nutrients = match entry.food.serve(&entry.serving) {
+                Ok(n) => fmt(n),
+                Err(_) => Default::default(),
+            };
+            let mut cells = vec![entry.food.name.clone(), entry.serving.to_string()];
+            cells.extend(nutrients);
+            Row::new(cells)
+        });
+        let total = match self.journal.nutrients() {
+            Ok(n) => fmt(n),
+            Err(err) => {
+                self.status = format!("{err:#}");
+                Default::default()
+            }
+        };
+        let mut total_cells = vec![Cell::from("Total"), Cell::from("")];
+        total_cells.extend(total.into_iter().map(Cell::from));
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(7),
+                Constraint::Length(7),
+                Constraint::Length(6),
+            ],
+        )
+        .header(
+            Row::new(["name", "serving", "carb", "fat", "protein", "kcal"])
+                .style(Style::new().add_modifier(Modifier::UNDERLINED)),
+        )
+        .footer(Row::new(total_cells).style(Style::new().add_modifier(Modifier::BOLD)))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title("Journal"));
+        frame.render_stateful_widget(table, area, &mut self.selected);
+    }
+}
+
+// Foods matching a search query, best match first.
+// This matches the same way as Database::find_food, but without reloading foods.
+fn matches<'f>(foods: &'f [(String, String)], query: &str) -> Vec<&'f (String, String)> {
+    let mut res: Vec<_> = foods
+        .iter()
+        .filter_map(|food| Some((fuzzy_score(query, &food.0, &food.1)?, food)))
+        .collect();
+    res.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+    res.into_iter().map(|(_, food)| food).collect()
+}
+
+fn draw_search(
+    frame: &mut Frame,
+    area: Rect,
+    foods: &[(String, String)],
+    query: &str,
+    selected: &mut ListState,
+) {
+    let area = area.centered(Constraint::Percentage(80), Constraint::Percentage(80));
+    frame.render_widget(Clear, area);
+    let [input, list] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+    frame.render_widget(
+        Paragraph::new(format!("{query}_")).block(Block::bordered().title("Search")),
+        input,
+    );
+    let items: Vec<_> = matches(foods, query)
+        .into_iter()
+        .map(|(key, name)| format!("{key} ({name})"))
+        .collect();
+    frame.render_stateful_widget(
+        List::new(items)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered()),
+        list,
+        selected,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        terminal.backend().to_string()
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(KeyCode::Char(c).into());
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_tui_journal() {
+        let (data, _tmp) = crate::tests::setup();
+        let mut app = App::new(&data, date(2024, 7, 1)).unwrap();
+        insta::assert_snapshot!(render(&mut app));
+
+        app.handle_key(KeyCode::Left.into());
+        insta::assert_snapshot!(render(&mut app));
+
+        press(&mut app, "q");
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn test_tui_add() {
+        let (data, _tmp) = crate::tests::setup();
+        let mut app = App::new(&data, date(2024, 7, 8)).unwrap();
+
+        press(&mut app, "aotml");
+        insta::assert_snapshot!(render(&mut app));
+
+        app.handle_key(KeyCode::Enter.into());
+        press(&mut app, "0.5 cups");
+        insta::assert_snapshot!(render(&mut app));
+
+        app.handle_key(KeyCode::Enter.into());
+        insta::assert_snapshot!(render(&mut app));
+
+        let journal = data.load_journal(&date(2024, 7, 8)).unwrap().unwrap();
+        assert_eq!(
+            journal
+                .0
+                .iter()
+                .map(|e| (e.key.as_str(), e.serving.to_string()))
+                .collect::<Vec<_>>(),
+            vec![("banana_oatmeal", "0.5 cups".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_tui_invalid_serving() {
+        let (data, _tmp) = crate::tests::setup();
+        let mut app = App::new(&data, date(2024, 7, 8)).unwrap();
+
+        press(&mut app, "abanana");
+        app.handle_key(KeyCode::Enter.into());
+        press(&mut app, "2 cups");
+        app.handle_key(KeyCode::Enter.into());
+        insta::assert_snapshot!(render(&mut app));
+        assert!(data.load_journal(&date(2024, 7, 8)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tui_remove() {
+        let (data, _tmp) = crate::tests::setup();
+        let mut app = App::new(&data, date(2024, 6, 30)).unwrap();
+
+        app.handle_key(KeyCode::Down.into());
+        press(&mut app, "d");
+
+        let journal = data.load_journal(&date(2024, 6, 30)).unwrap().unwrap();
+        assert_eq!(
+            journal.0.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(),
+            vec!["banana"]
+        );
+    }
+
+    #[test]
+    fn test_tui_save_fails() {
+        let (data, tmp) = crate::tests::setup();
+        let mut app = App::new(&data, date(2024, 6, 30)).unwrap();
+        // Saving fails if the journal can't be read, as it's a directory.
+        let path = tmp.path().join("journal/2024/06/30.txt");
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+
+        // The journal shown is left as it was.
+        press(&mut app, "d");
+        assert_eq!(app.journal.0.len(), 2);
+        press(&mut app, "abanana");
+        app.handle_key(KeyCode::Enter.into());
+        press(&mut app, "1");
+        app.handle_key(KeyCode::Enter.into());
+        assert_eq!(app.journal.0.len(), 2);
+    }
+}
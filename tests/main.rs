@@ -1,10 +1,11 @@
+use std::os::unix::ffi::OsStrExt;
 use std::process::Command;
 use std::{fs, io::Write, os::unix::fs::OpenOptionsExt, path::Path};
 
 use insta::internals::SettingsBindDropGuard;
 use insta_cmd::{assert_cmd_snapshot, get_cargo_bin};
 
-struct CLI {
+struct Cli {
     data_dir: tempfile::TempDir,
     _settings: SettingsBindDropGuard,
 }
@@ -24,7 +25,7 @@ fn cp(src: impl AsRef<Path>, dst: impl AsRef<Path>) {
     }
 }
 
-impl CLI {
+impl Cli {
     fn new() -> Self {
         let _ = env_logger::try_init();
 
@@ -32,6 +33,9 @@ impl CLI {
 
         // Remove ansi color codes
         settings.add_filter("\x1b\\[\\d+m", "");
+        // Foods eaten today are saved with the current time.
+        settings.add_filter(r"│ \d{2}:\d{2} │", "│ HH:MM │");
+        settings.add_filter(r"\| \d{2}:\d{2}", "| HH:MM");
 
         let cli = Self {
             data_dir: tempfile::tempdir().unwrap(),
@@ -46,6 +50,7 @@ impl CLI {
     fn cmd(&self) -> Command {
         let mut cmd = Command::new(get_cargo_bin(env!("CARGO_PKG_NAME")));
         cmd.env("XDG_DATA_HOME", self.data_dir.path());
+        cmd.env("XDG_CACHE_HOME", self.data_dir.path().join("cache"));
         cmd
     }
 
@@ -60,8 +65,11 @@ impl CLI {
             .success())
     }
 
-    fn edit(&self, kind: &str, key: &str, content: &str) {
-        let editor = format!("#!/bin/sh\necho -e {content:?} > $1");
+    // A command that runs an editor which replaces the file with content.
+    // The file as it was given to the editor is copied to buffer.txt.
+    fn editor(&self, content: &str) -> Command {
+        let buffer = self.data_dir.path().join("buffer.txt");
+        let editor = format!("#!/bin/sh\ncp $1 {buffer:?}\nprintf '%b\\n' {content:?} > $1");
         let path = self.data_dir.path().join("editor");
         std::fs::OpenOptions::new()
             .create(true)
@@ -75,6 +83,11 @@ impl CLI {
         log::debug!("Test wrote fake editor to {path:?}:\n{editor:?}");
         let mut cmd = self.cmd();
         cmd.env("EDITOR", path);
+        cmd
+    }
+
+    fn edit(&self, kind: &str, key: &str, content: &str) {
+        let mut cmd = self.editor(content);
         cmd.args([kind, "edit", key]);
         assert!(cmd.spawn().unwrap().wait().unwrap().success());
     }
@@ -88,37 +101,49 @@ impl CLI {
 
 #[test]
 fn test_food_show_missing() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["food", "show", "nope"]));
 }
 
 #[test]
 fn test_food_show() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["food", "show", "oats"]));
 }
 
+#[test]
+fn test_food_show_no_kcal() {
+    let cli = Cli::new();
+    // Without kcal, it is computed from the other nutrients.
+    cli.edit(
+        "food",
+        "lentils",
+        "name = Lentils\n[nutrients]\ncarb = 10\nprotein = 5",
+    );
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "lentils"]));
+}
+
 #[test]
 fn test_food_ls() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["food", "ls"]));
 }
 
 #[test]
 fn test_food_ls_pattern() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["food", "ls", "oat"]));
 }
 
 #[test]
 fn test_food_ls_pattern_nomatch() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["food", "ls", "nope"]));
 }
 
 #[test]
 fn test_food_rm() {
-    let cli = CLI::new();
+    let cli = Cli::new();
 
     assert_cmd_snapshot!(cli.cmd().args(["food", "rm", "banana_oatmeal"]));
     assert_cmd_snapshot!(cli.cmd().args(["food", "ls"]));
@@ -126,13 +151,13 @@ fn test_food_rm() {
 
 #[test]
 fn test_food_rm_not_exist() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["food", "rm", "nope"]));
 }
 
 #[test]
 fn test_food_edit_new() {
-    let cli = CLI::new();
+    let cli = Cli::new();
 
     cli.edit(
         "food",
@@ -150,7 +175,7 @@ kcal = 16
 
 #[test]
 fn test_food_edit_existing() {
-    let cli = CLI::new();
+    let cli = Cli::new();
 
     cli.edit(
         "food",
@@ -170,15 +195,41 @@ cups = 2.5"#,
     assert_cmd_snapshot!(cli.cmd().args(["food", "show", "oats"]));
 }
 
+#[test]
+fn test_food_rm_used() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["food", "rm", "banana"]));
+    // Still used by a recipe, so the journals are left as they were.
+    assert_cmd_snapshot!(cli.cmd().args(["food", "rm", "--snapshot", "banana"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+    assert_eq!(
+        fs::read_to_string(cli.data_dir.path().join("nosh/journal/2024/07/01.txt")).unwrap(),
+        "banana = 1\noats = 0.5c\noats = 1.0\nbanana = 50g\n"
+    );
+    assert_cmd_snapshot!(cli.cmd().args(["food", "rm", "--force", "banana"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+}
+
+#[test]
+fn test_food_mv() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["food", "mv", "--dry-run", "banana", "plantain"]));
+    cli.run(&["food", "mv", "banana", "plantain"]);
+    assert_cmd_snapshot!(cli.cmd().args(["food", "ls"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+}
+
 #[test]
 fn test_eat_missing() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["eat", "nope"]));
 }
 
 #[test]
 fn test_eat() {
-    let cli = CLI::new();
+    let cli = Cli::new();
 
     cli.run(&["eat", "oats"]);
     cli.run(&["eat", "oats", "2.5"]);
@@ -190,11 +241,119 @@ fn test_eat() {
     assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
 }
 
+#[test]
+fn test_eat_several() {
+    let cli = Cli::new();
+    cli.run(&["eat", "oats", "0.5c", "banana", "oats=100g", "banana", "2"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+
+    // Nothing is added if any food or serving is invalid.
+    assert_cmd_snapshot!(cli.cmd().args(["eat", "banana", "oats", "3spoons"]));
+    assert_cmd_snapshot!(cli.cmd().args(["eat", "banana", "nope"]));
+    assert_cmd_snapshot!(cli.cmd().args(["eat", "banana", "1", "2"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+}
+
+#[test]
+fn test_eat_digit_key() {
+    let cli = Cli::new();
+    cli.edit(
+        "food",
+        "7up",
+        "name = 7 Up\n[nutrients]\ncarb = 38\nkcal = 150",
+    );
+    // A food key that looks like a serving is still a food.
+    cli.run(&["eat", "7up"]);
+    cli.run(&["eat", "oats", "7up", "2"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+}
+
+#[test]
+fn test_eat_note() {
+    let cli = Cli::new();
+    cli.run(&["eat", "oats", "0.5c", "--note", "weighed dry"]);
+    cli.run(&["eat", "banana"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+
+    let today = chrono::Local::now().date_naive();
+    let path = format!("nosh/journal/{}.txt", today.format("%Y/%m/%d"));
+    insta::assert_snapshot!(fs::read_to_string(cli.data_dir.path().join(path)).unwrap());
+}
+
+#[test]
+fn test_eat_quick() {
+    let cli = Cli::new();
+    cli.run(&[
+        "eat",
+        "--kcal",
+        "650",
+        "--protein",
+        "30",
+        "Restaurant burger",
+    ]);
+    cli.run(&["eat", "--kcal", "400", "restaurant", "burger"]);
+    // Keys of foods in the database aren't reused, and kcal is computed.
+    cli.run(&["eat", "--carb", "10", "--fat", "5", "banana"]);
+    cli.run(&["eat", "banana"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["eat", "--kcal", "1", "--meal", "breakfast"]));
+
+    let today = chrono::Local::now().date_naive();
+    let path = format!("nosh/journal/{}.txt", today.format("%Y/%m/%d"));
+    insta::assert_snapshot!(fs::read_to_string(cli.data_dir.path().join(path)).unwrap());
+}
+
+#[test]
+fn test_eat_fuzzy() {
+    let cli = Cli::new();
+    // Without a terminal to confirm a match, even a single one isn't used.
+    assert_cmd_snapshot!(cli.cmd().args(["eat", "oast", "0.5c"]));
+    assert_cmd_snapshot!(cli.cmd().args(["eat", "oat"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+}
+
+#[test]
+fn test_food_show_fuzzy() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "bnoat"]));
+}
+
+#[test]
+fn test_food_alias() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["food", "ls", "porr"]));
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "porridge"]));
+    cli.run(&["eat", "porridge", "0.5c"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+    assert_cmd_snapshot!(cli.cmd().args(["food", "edit", "oatmeal"]));
+}
+
+#[test]
+fn test_food_edit_recipe() {
+    let cli = Cli::new();
+    assert!(cli
+        .editor("name = Granola\n[ingredients]\noats = 1")
+        .args(["food", "edit", "--recipe", "granola"])
+        .status()
+        .unwrap()
+        .success());
+    // The buffer starts with a recipe, followed by help that isn't saved.
+    let buffer = fs::read_to_string(cli.data_dir.path().join("buffer.txt")).unwrap();
+    insta::assert_snapshot!(buffer);
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "granola"]));
+    let path = cli.data_dir.path().join("nosh/food/granola.txt");
+    assert!(!fs::read_to_string(path).unwrap().contains("##!"));
+
+    assert_cmd_snapshot!(cli.cmd().args(["food", "edit", "--recipe", "oats"]));
+}
+
 #[test]
 fn test_food_search() {
     use httptest::{matchers::*, responders::*, Expectation, Server};
 
-    let cli = CLI::new();
+    let cli = Cli::new();
     let server = Server::run();
     server.expect(
         Expectation::matching(request::method_path("GET", "/test")).respond_with(
@@ -204,25 +363,211 @@ fn test_food_search() {
     );
     let url = server.url("/test");
 
-    assert_cmd_snapshot!(
-        cli.search(&url.to_string())
-            .args(["food", "search", "potato"])
-            .pass_stdin("1") // select result 1
-    );
+    assert_cmd_snapshot!(cli
+        .search(&url.to_string())
+        .args(["food", "search", "--pick", "1", "potato"]));
 
     // The food should have been added.
     assert_cmd_snapshot!(cli.cmd().args(["food", "show", "potato"]));
 }
 
+#[test]
+fn test_food_search_json() {
+    use httptest::{matchers::*, responders::*, Expectation, Server};
+
+    let cli = Cli::new();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/test")).respond_with(
+            status_code(200)
+                .body(fs::read_to_string("tests/testdata/search/branded/page1.json").unwrap()),
+        ),
+    );
+    let url = server.url("/test");
+
+    assert_cmd_snapshot!(cli
+        .search(&url.to_string())
+        .args(["food", "search", "--json", "potato"]));
+}
+
+#[test]
+fn test_food_search_no_tty() {
+    use httptest::{matchers::*, responders::*, Expectation, Server};
+
+    let cli = Cli::new();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/test"))
+            .times(2)
+            .respond_with(
+                status_code(200).body(
+                    fs::read_to_string("tests/testdata/search/foundation/page1.json").unwrap(),
+                ),
+            ),
+    );
+    let url = server.url("/test");
+
+    // Without a terminal, results are printed without prompting.
+    assert_cmd_snapshot!(cli
+        .search(&url.to_string())
+        .args(["food", "search", "potato"])
+        .pass_stdin("1"));
+
+    // Nothing should have been added.
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "potato"]));
+
+    // The key is searched for as is, even if it looks like a flag.
+    assert_cmd_snapshot!(cli
+        .search(&url.to_string())
+        .args(["food", "edit", "--from-search", "--", "-potato"])
+        .pass_stdin("1"));
+}
+
+#[test]
+fn test_food_search_off() {
+    use httptest::{matchers::*, responders::*, Expectation, Server};
+
+    let cli = Cli::new();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/test")).respond_with(
+            status_code(200)
+                .body(fs::read_to_string("tests/testdata/search/off/page1.json").unwrap()),
+        ),
+    );
+    let url = server.url("/test");
+
+    assert_cmd_snapshot!(cli.search(&url.to_string()).args([
+        "food",
+        "search",
+        "--provider",
+        "off",
+        "--first",
+        "rolled_oats",
+        "oats"
+    ]));
+
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "rolled_oats"]));
+}
+
+#[test]
+fn test_food_search_offline() {
+    let cli = Cli::new();
+
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["fdc", "import", "tests/testdata/fdc/foundation.json"]));
+    assert_cmd_snapshot!(cli.cmd().args(["fdc", "import", "tests/testdata/fdc/csv"]));
+    assert_cmd_snapshot!(cli.cmd().args([
+        "food",
+        "search",
+        "--offline",
+        "--pick",
+        "1",
+        "potato",
+        "potato raw"
+    ]));
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "potato"]));
+}
+
+#[test]
+fn test_check() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().arg("check"));
+
+    let dir = cli.data_dir.path().join("nosh");
+    fs::write(
+        dir.join("food/granola.txt"),
+        "name = Granola\n[ingredients]\noast = 2\nbanana = 1 cup\n",
+    )
+    .unwrap();
+    fs::write(dir.join("food/broken.txt"), "name = Broken\n[nutrients\n").unwrap();
+    fs::write(dir.join("food/notes.md"), "").unwrap();
+    fs::write(
+        dir.join(std::ffi::OsStr::from_bytes(b"food/\xff.txt")),
+        "name = Bad",
+    )
+    .unwrap();
+    fs::write(dir.join("journal/2024/07/02.txt"), "nope = 1\noats = x\n").unwrap();
+    fs::write(dir.join("journal/2024/07/40.txt"), "oats = 1\n").unwrap();
+    assert_cmd_snapshot!(cli.cmd().arg("check"));
+}
+
+#[test]
+fn test_log_no_git() {
+    let cli = Cli::new();
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"git init [^`]+", "git init [DIR]");
+    settings.bind(|| assert_cmd_snapshot!(cli.cmd().arg("log")));
+}
+
+#[test]
+fn test_log_undo() {
+    let cli = Cli::new();
+    let dir = cli.data_dir.path().join("nosh");
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "--quiet"]);
+    git(&["config", "user.name", "nosh"]);
+    git(&["config", "user.email", "nosh@example.com"]);
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "--message", "init"]);
+
+    cli.edit("journal", "2024-07-01", "oats = 1.5c");
+    cli.run(&["food", "mv", "banana", "plantain"]);
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[0-9a-f]{7,} \d{4}-\d{2}-\d{2} \d{2}:\d{2}", "[COMMIT]");
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli.cmd().arg("log"));
+        assert_cmd_snapshot!(cli.cmd().arg("undo"));
+        assert_cmd_snapshot!(cli.cmd().arg("undo"));
+        assert_cmd_snapshot!(cli.cmd().args(["log", "-n", "3"]));
+    });
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+}
+
+#[test]
+fn test_commit_fails() {
+    let cli = Cli::new();
+    let dir = cli.data_dir.path().join("nosh");
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["init", "--quiet"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    // A lock held by another git process makes committing fail.
+    fs::write(dir.join(".git/index.lock"), "").unwrap();
+
+    let out = cli.cmd().args(["eat", "oats"]).output().unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(out.status.success(), "{stderr}");
+    assert!(
+        stderr.starts_with("Warning: saved, but failed to record \"eat oats")
+            && stderr.contains("so `nosh undo` won't revert it"),
+        "{stderr}"
+    );
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+}
+
 #[test]
 fn test_journal_show() {
-    let cli = CLI::new();
+    let cli = Cli::new();
     assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
 }
 
 #[test]
 fn test_journal_edit() {
-    let cli = CLI::new();
+    let cli = Cli::new();
 
     cli.edit(
         "journal",
@@ -234,3 +579,253 @@ banana = 1
     );
     assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
 }
+
+#[test]
+fn test_journal_edit_invalid() {
+    let cli = Cli::new();
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"saved to \S+\.txt", "saved to [TMP]");
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli
+            .editor("oats = 1.5c\nbanana = 2 spoons\nnope = 1")
+            .args(["journal", "edit", "2024-07-01"])
+            // Keep the saved changes with the rest of the test data, to be cleaned up.
+            .env("TMPDIR", cli.data_dir.path()))
+    });
+    // The journal is unchanged.
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+}
+
+#[test]
+fn test_journal_rm() {
+    let cli = Cli::new();
+    let date = ["--date", "2024-07-01"];
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "rm", "1"]).args(date));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "rm", "banana"]).args(date));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "rm", "5"]).args(date));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+}
+
+#[test]
+fn test_journal_set() {
+    let cli = Cli::new();
+    let date = ["--date", "2024-07-01"];
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "set", "1", "1c"]).args(date));
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["journal", "set", "0", "2 spoons"])
+        .args(date));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+}
+
+#[test]
+fn test_journal_copy() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args([
+        "journal",
+        "copy",
+        "2024-07-01",
+        "2024-06-30",
+        "--entries",
+        "3,0"
+    ]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-06-30"]));
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["journal", "copy", "2024-07-01", "--entries", "4"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "copy", "2024-07-05"]));
+
+    // Repeating a day copies it to today.
+    cli.run(&["eat", "--repeat", "2024-06-30"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+}
+
+#[test]
+fn test_meal() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["meal", "ls"]));
+    assert_cmd_snapshot!(cli.cmd().args(["meal", "show", "breakfast"]));
+    assert_cmd_snapshot!(cli.cmd().args([
+        "meal",
+        "save",
+        "lunch",
+        "--date",
+        "2024-07-01",
+        "--entries",
+        "0,2"
+    ]));
+    assert_cmd_snapshot!(cli.cmd().args(["meal", "show", "lunch"]));
+
+    cli.run(&["eat", "--meal", "breakfast"]);
+    cli.run(&["eat", "--meal", "lunch"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+
+    cli.run(&["meal", "rm", "lunch"]);
+    assert_cmd_snapshot!(cli.cmd().args(["eat", "--meal", "lunch"]));
+}
+
+#[test]
+fn test_uneat() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["uneat", "--date", "2024-07-01"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+
+    // Without history, undo removes the last food eaten today.
+    cli.run(&["eat", "oats", "0.5c"]);
+    cli.run(&["eat", "banana"]);
+    cli.run(&["undo"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+}
+
+#[test]
+fn test_comments_kept() {
+    let cli = Cli::new();
+    let path = cli.data_dir.path().join("nosh/journal/2024/07/01.txt");
+    fs::write(
+        &path,
+        "# breakfast\nbanana = 1\noats = 0.5c\n\n# lunch\noats=1.0\nbanana = 50g\n",
+    )
+    .unwrap();
+    cli.run(&["journal", "set", "2", "2c", "--date", "2024-07-01"]);
+    cli.run(&["journal", "rm", "0", "--date", "2024-07-01"]);
+    insta::assert_snapshot!(fs::read_to_string(&path).unwrap());
+
+    // Comments written while editing are saved too, without the guide.
+    assert!(cli
+        .editor("; Bob's Red Mill\nname = Oats\n[nutrients]\ncarb = 68.7\n")
+        .args(["food", "edit", "oats"])
+        .status()
+        .unwrap()
+        .success());
+    let path = cli.data_dir.path().join("nosh/food/oats.txt");
+    insta::assert_snapshot!(fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+fn test_weigh() {
+    let cli = Cli::new();
+    cli.run(&["weigh", "81.5kg", "--date", "2024-06-28"]);
+    cli.run(&["weigh", "180", "--unit", "lb", "--date", "2024-06-30"]);
+    cli.run(&["weigh", "81", "--date", "2024-07-01"]);
+    cli.run(&["measure", "waist", "86cm", "--date", "2024-07-01"]);
+    let path = cli.data_dir.path().join("nosh/body/2024/07/01.txt");
+    insta::assert_snapshot!(fs::read_to_string(path).unwrap());
+
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["report", "--date", "2024-07-02", "-n", "4"]));
+    assert_cmd_snapshot!(cli.cmd().args([
+        "report",
+        "--date",
+        "2024-07-01",
+        "-n",
+        "2",
+        "--unit",
+        "lb"
+    ]));
+    assert_cmd_snapshot!(cli.cmd().args(["weigh", "81 stone"]));
+}
+
+#[test]
+fn test_tdee() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["tdee", "--date", "2024-07-01"]));
+    cli.run(&["weigh", "82kg", "--date", "2024-06-24"]);
+    cli.run(&["weigh", "81.9kg", "--date", "2024-06-30"]);
+    cli.run(&["weigh", "81.8kg", "--date", "2024-07-01"]);
+    assert_cmd_snapshot!(cli.cmd().args(["tdee", "--date", "2024-07-01", "-n", "2"]));
+    assert_cmd_snapshot!(cli.cmd().args([
+        "tdee",
+        "--date",
+        "2024-07-01",
+        "-n",
+        "2",
+        "--unit",
+        "lb"
+    ]));
+    // Too many days to go back from the date is an error, not a panic.
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["tdee", "--date", "2024-07-01", "-n", "99999999999"]));
+    assert_cmd_snapshot!(cli.cmd().args(["report", "-n", "99999999999"]));
+}
+
+#[test]
+fn test_burn() {
+    let cli = Cli::new();
+    cli.edit("activity", "running", "name = Running\nmet = 10");
+    cli.edit("activity", "rowing", "name = Rowing\nkcal_per_min = 8");
+    assert_cmd_snapshot!(cli.cmd().args(["activity", "ls"]));
+
+    // Running burns energy based on weight, which isn't known yet.
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["burn", "running", "30min", "--date", "2024-07-01"]));
+    cli.run(&["weigh", "80kg", "--date", "2024-06-30"]);
+    assert_cmd_snapshot!(cli
+        .cmd()
+        .args(["burn", "running", "30min", "--date", "2024-07-01"]));
+    cli.run(&["burn", "rowing", "0.5h", "--date", "2024-07-01"]);
+    cli.run(&["burn", "200kcal", "--date", "2024-07-01"]);
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+    // The kcal are recorded, so changing an activity doesn't change past days.
+    cli.edit("activity", "running", "name = Running\nmet = 20");
+    fs::remove_file(cli.data_dir.path().join("nosh/activity/rowing.txt")).unwrap();
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01"]));
+
+    assert_cmd_snapshot!(cli.cmd().args(["burn", "swimming", "1h"]));
+    assert_cmd_snapshot!(cli.cmd().args(["burn", "45"]));
+
+    fs::write(
+        cli.data_dir.path().join("nosh/exercise/2024/07/02.txt"),
+        "swimming = 1h\nrunning = 10 laps\nexercise = 100 kcal\n",
+    )
+    .unwrap();
+    assert_cmd_snapshot!(cli.cmd().arg("check"));
+    // Exercise whose kcal can't be worked out is still shown.
+    fs::write(
+        cli.data_dir.path().join("nosh/exercise/2024/07/02.txt"),
+        "swimming = 1h\nexercise = 100 kcal\n",
+    )
+    .unwrap();
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-02"]));
+}
+
+#[test]
+fn test_drink() {
+    let cli = Cli::new();
+    cli.edit(
+        "food",
+        "milk",
+        "name = Milk\nfluid = 240 ml\n[nutrients]\nkcal = 150\nprotein = 8\n[servings]\ncup = 1",
+    );
+    cli.edit(
+        "food",
+        "juice",
+        "name = Juice\nfluid = 200 ml\n[nutrients]\nkcal = 90",
+    );
+    cli.edit("food", "oats", "name = Oats\n[nutrients]\nkcal = 150");
+
+    assert_cmd_snapshot!(cli.cmd().args(["goal"]));
+    assert_cmd_snapshot!(cli.cmd().args(["drink", "500ml"]));
+    // Volumes are converted to a unit the food has, or to servings by its fluid.
+    assert_cmd_snapshot!(cli.cmd().args(["drink", "milk", "120ml"]));
+    assert_cmd_snapshot!(cli.cmd().args(["drink", "juice", "300ml"]));
+    assert_cmd_snapshot!(cli.cmd().args(["goal", "fluid", "2.5l"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show"]));
+
+    assert_cmd_snapshot!(cli.cmd().args(["drink", "oats", "100ml"]));
+    assert_cmd_snapshot!(cli.cmd().args(["drink", "2"]));
+    assert_cmd_snapshot!(cli.cmd().args(["goal", "fluid", "2kg"]));
+}
+
+#[test]
+fn test_daily_value() {
+    let cli = Cli::new();
+    assert_cmd_snapshot!(cli.cmd().args(["food", "show", "oats", "--dv"]));
+    cli.run(&["goal", "kcal", "2500"]);
+    cli.run(&["goal", "protein", "100"]);
+    assert_cmd_snapshot!(cli.cmd().args(["goal"]));
+    assert_cmd_snapshot!(cli.cmd().args(["journal", "show", "2024-07-01", "--dv"]));
+    assert_cmd_snapshot!(cli.cmd().args(["goal", "fiber", "30"]));
+}